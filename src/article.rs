@@ -1,30 +1,27 @@
-use std::{collections::HashMap, io::{self, Write}};
+use std::collections::HashMap;
 use colored::Colorize;
 use regex::Regex;
 
 use crate::constants;
+use crate::month::Month;
+use crate::names::{self, Name};
+use crate::printer::{Field, Value};
+use crate::tex;
 
 pub struct Article {
-    author: String,
+    author: Vec<Name>,
     title: String,
     journal: String,
     volume: i32,
     number: i32,
-    month: String,
+    month: Month,
     year: i32,
     pages: String,
     doi: String
 }
 
 impl Article {
-    pub fn new(input: &str ) -> Result<Article, String> {
-        let re = Regex::new(constants::REGEX_ENTRY).unwrap();
-        let mut fields: HashMap<String, String> = HashMap::new();
-
-        for cap in re.captures_iter(input) {
-            fields.insert(cap[1].to_string(), cap[2].trim_matches('{').trim_matches('}').to_string());
-        }
-
+    pub fn new(fields: &HashMap<String, String>) -> Result<Article, String> {
         let author = fields.get("author").and_then(|s| if !s.is_empty() { Some(s) } else { None }).ok_or("Missing or empty author field")?.to_string();
         let title = fields.get("title").and_then(|s| if !s.is_empty() { Some(s) } else { None }).ok_or("Missing or empty title field")?.to_string();
         let journal = fields.get("journal").and_then(|s| if !s.is_empty() { Some(s) } else { None }).ok_or("Missing or empty journal field")?.to_string();
@@ -38,8 +35,7 @@ impl Article {
             Some(_) => String::new(),
             None => String::new(),
         };
-        let mut month = fields.get("month").and_then(|s| if !s.is_empty() { Some(s) } else { None }).ok_or("Missing or empty month field")?.to_string();
-        month.truncate(3);
+        let month = fields.get("month").and_then(|s| if !s.is_empty() { Some(s) } else { None }).ok_or("Missing or empty month field")?.to_string();
         let year = fields.get("year").and_then(|s| if !s.is_empty() { Some(s) } else { None }).ok_or("Missing or empty year field")?.to_string();
         let pages = match fields.get("pages") {
             Some(s) if !s.is_empty() => s.to_string(),
@@ -52,34 +48,28 @@ impl Article {
             None => String::new(),
         };
 
-        let author_re = Regex::new(constants::REGEX_AUTHOR).unwrap();
         let title_re = Regex::new(constants::REGEX_TITLE).unwrap();
         let journal_re = Regex::new(constants::REGEX_TITLE).unwrap();
-        let month_re = Regex::new(constants::REGEX_MONTH).unwrap();
         let pages_re = Regex::new(constants::REGEX_PAGES).unwrap();
         let doi_re = Regex::new(constants::REGEX_DOI).unwrap();
 
-        if !author_re.is_match(&author) {
-            return Err(format!("{} Invalid authors format", constants::ERR.red()));
-        }
+        let author = names::parse_list(&author).map_err(|e| format!("{} Invalid authors format: {}", constants::ERR.red(), e))?;
 
-        if !title_re.is_match(&title) {
+        if !title_re.is_match(&tex::to_plain(&title)) {
             return Err(format!("{} Invalid title format", constants::ERR.red()));        
         }
 
-        if !journal_re.is_match(&journal) {
+        if !journal_re.is_match(&tex::to_plain(&journal)) {
             return Err(format!("{} Invalid journal format", constants::ERR.red()));
         }
 
-        if !month_re.is_match(&month) {
-            return Err(format!("{} Invalid month format", constants::ERR.red()));
-        }
+        let month = Month::parse(&month).map_err(|e| format!("{} Invalid month format: {}", constants::ERR.red(), e))?;
 
         if !pages_re.is_match(&pages) {
             return Err(format!("{} Invalid pages format", constants::ERR.red()));
         }
 
-        if pages == "" || pages.is_empty() {
+        if pages.is_empty() {
             eprintln!("{} Non present page number in entry with title: {}", constants::WARN.yellow(), title);
         } else if !pages_re.is_match(&pages) {
             return Err(format!("{} Invalid pages format: |{}|", constants::ERR.red(), pages));
@@ -132,31 +122,17 @@ impl Article {
         })
     }
 
-    pub fn print<W: Write>(&self, writer: &mut W) -> io::Result<()> {
-        writeln!(writer, "@article{{{},", self.generate_key())?;
-        writeln!(writer, "    author         = {{{}}},", self.author)?;
-        writeln!(writer, "    title          = {{{{{}}}}},", self.title)?;
-        writeln!(writer, "    journal        = {{{{{}}}}},", self.journal)?;
-        if self.volume != -1 {
-            writeln!(writer, "    volume         = {{{}}},", self.volume)?;
-        } else {
-            writeln!(writer, "    volume         = {{}},")?;
-        }
-        if self.number != -1 {
-            writeln!(writer, "    number         = {{{}}},", self.number)?;
-        } else {
-            writeln!(writer, "    number         = {{}},")?;
-        }
-        writeln!(writer, "    month          = {},", self.month)?;
-        writeln!(writer, "    year           = {{{}}},", self.year)?;
-        writeln!(writer, "    pages          = {{{}}},", self.pages)?;
-        writeln!(writer, "    doi            = {{{}}}", self.doi)?;
-        writeln!(writer, "}}")?;
-        Ok(())
-    }
-
-    fn generate_key(&self) -> String {
-        let first_author_last_name = self.author.split(',').next().unwrap_or("").trim();
-        format!("{}{}", first_author_last_name.to_lowercase(), self.year)
+    pub fn fields(&self) -> Vec<Field> {
+        vec![
+            ("author".to_string(), Value::Text(names::format_list(&self.author))),
+            ("title".to_string(), Value::Protected(self.title.clone())),
+            ("journal".to_string(), Value::Protected(self.journal.clone())),
+            ("volume".to_string(), Value::Text(if self.volume != -1 { self.volume.to_string() } else { String::new() })),
+            ("number".to_string(), Value::Text(if self.number != -1 { self.number.to_string() } else { String::new() })),
+            ("month".to_string(), Value::Macro(self.month.to_bibtex())),
+            ("year".to_string(), Value::Text(self.year.to_string())),
+            ("pages".to_string(), Value::Text(self.pages.clone())),
+            ("doi".to_string(), Value::Text(self.doi.clone())),
+        ]
     }
 }
\ No newline at end of file
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use regex::Regex;
+
+use crate::constants;
+
+#[derive(Clone)]
+pub struct RawEntry {
+    pub entry_type: String,
+    pub key: String,
+    pub fields: Vec<(String, String)>,
+    /// Fields written without braces or quotes: macros such as `jan` or `jcomp`, numbers
+    /// and concatenations.
+    pub macros: Vec<String>,
+    /// Field values as written, with their delimiters and inner braces.
+    pub raw_values: Vec<(String, String)>,
+    pub sources: Vec<String>,
+    pub offset: usize,
+    pub text: String
+}
+
+impl RawEntry {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    pub fn raw_value(&self, name: &str) -> Option<&str> {
+        self.raw_values.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    pub fn field_map(&self) -> HashMap<String, String> {
+        self.fields.iter().cloned().collect()
+    }
+
+    /// Whether this is a `@string`, `@preamble` or `@comment` block rather than a reference.
+    pub fn is_special(&self) -> bool {
+        SPECIAL_TYPES.contains(&self.entry_type.as_str())
+    }
+}
+
+/// Block types of the BibTeX language that define macros or hold text instead of a reference.
+pub const SPECIAL_TYPES: [&str; 3] = ["string", "preamble", "comment"];
+
+/// A piece of a .bib file: an entry, or the text (comments, blank lines, free text)
+/// between two entries, kept exactly as read.
+pub enum Block {
+    Text(String),
+    Entry(RawEntry)
+}
+
+pub fn parse_entries(input: &str) -> Vec<RawEntry> {
+    parse_document(input).into_iter().filter_map(|block| match block {
+        Block::Entry(entry) => Some(entry),
+        Block::Text(_) => None,
+    }).collect()
+}
+
+pub fn parse_document(input: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut text_start = 0;
+    let mut pos = 0;
+
+    while let Some(offset) = input[pos..].find('@') {
+        let start = pos + offset;
+        match parse_entry_at(input, start) {
+            Some(entry) => {
+                if start > text_start {
+                    blocks.push(Block::Text(input[text_start..start].to_string()));
+                }
+                pos = start + entry.text.len();
+                text_start = pos;
+                blocks.push(Block::Entry(entry));
+            }
+            None => pos = start + 1,
+        }
+    }
+    if text_start < input.len() {
+        blocks.push(Block::Text(input[text_start..].to_string()));
+    }
+
+    blocks
+}
+
+/// Parses the entry starting at the `@` at byte `start`, if there is a well-formed one.
+fn parse_entry_at(input: &str, start: usize) -> Option<RawEntry> {
+    let rest = &input[start + 1..];
+    let open = rest.find('{')?;
+    let entry_type = rest[..open].trim();
+    if entry_type.is_empty() || !entry_type.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+
+    let mut brace_count = 0;
+    let mut close = None;
+    for (i, c) in rest[open..].char_indices() {
+        if c == '{' {
+            brace_count += 1;
+        } else if c == '}' {
+            brace_count -= 1;
+            if brace_count == 0 {
+                close = Some(open + i);
+                break;
+            }
+        }
+    }
+    let close = close?;
+
+    let entry_content = &rest[open + 1..close];
+    let key: String = entry_content.chars().take_while(|&c| c != ',').collect();
+    let (fields, macros, raw_values) = parse_fields(entry_content);
+
+    Some(RawEntry {
+        entry_type: entry_type.to_lowercase(),
+        key: key.trim().to_string(),
+        fields,
+        macros,
+        raw_values,
+        sources: Vec::new(),
+        offset: start,
+        text: input[start..start + 1 + close + 1].to_string()
+    })
+}
+
+/// Pairs of field name and value.
+pub type Fields = Vec<(String, String)>;
+
+/// The fields of an entry, the names of those written without delimiters and the values as written.
+pub fn parse_fields(input: &str) -> (Fields, Vec<String>, Fields) {
+    let re = Regex::new(constants::REGEX_ENTRY).unwrap();
+    let mut fields: Fields = Vec::new();
+    let mut macros = Vec::new();
+    let mut raw_values: Fields = Vec::new();
+
+    for cap in re.captures_iter(input) {
+        let name = cap[1].to_lowercase();
+        let raw_value = cap[2].trim().trim_end_matches(',').trim();
+        macros.retain(|n| *n != name);
+        if !is_quoted(raw_value) && strip_group(raw_value) == raw_value {
+            macros.push(name.clone());
+        }
+        match raw_values.iter_mut().find(|(n, _)| *n == name) {
+            Some(field) => field.1 = raw_value.to_string(),
+            None => raw_values.push((name.clone(), raw_value.to_string())),
+        }
+        let value = unwrap_value(&name, raw_value);
+        match fields.iter_mut().find(|(n, _)| *n == name) {
+            Some(field) => field.1 = value,
+            None => fields.push((name, value)),
+        }
+    }
+
+    (fields, macros, raw_values)
+}
+
+/// Whether `value` is a single string in double quotes, not a concatenation such as `"a" # "b"`.
+/// Quotes inside brace groups, as in `{\"o}`, belong to the string.
+fn is_quoted(value: &str) -> bool {
+    if value.len() < 2 || !value.starts_with('"') || !value.ends_with('"') {
+        return false;
+    }
+    let mut depth = 0;
+    for c in value[1..value.len() - 1].chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            '"' if depth == 0 => return false,
+            _ => {}
+        }
+    }
+    true
+}
+
+/// Strips the delimiters around a value, and the inner braces of `{{...}}` or `"{...}"` except in
+/// name fields, where they mark a corporate author such as `{{World Health Organization}}`.
+fn unwrap_value(name: &str, value: &str) -> String {
+    let value = value.trim_end_matches(',').trim();
+    let mut value = if is_quoted(value) { &value[1..value.len() - 1] } else { strip_group(value) };
+    if name != "author" && name != "editor" {
+        value = strip_group(value);
+    }
+    value.to_string()
+}
+
+/// The content of `value` when it is a single brace group, otherwise `value` itself.
+fn strip_group(value: &str) -> &str {
+    if !value.starts_with('{') {
+        return value;
+    }
+    let mut depth = 0;
+    for (i, c) in value.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return if i == value.len() - 1 { &value[1..i] } else { value };
+                }
+            }
+            _ => {}
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::printer::{self, Format, Value};
+
+    #[test]
+    fn quoted_values_with_accents() {
+        let entry = &parse_entries("@book{a,\n  author = \"M{\\\"u}ller, J{\\\"o}rg\",\n  title = \"Tr{\\\"a}ume\"\n}\n")[0];
+        assert_eq!(entry.get("author"), Some("M{\\\"u}ller, J{\\\"o}rg"));
+        assert_eq!(entry.get("title"), Some("Tr{\\\"a}ume"));
+        assert!(entry.macros.is_empty());
+    }
+
+    #[test]
+    fn round_trip() {
+        let fields = vec![
+            ("author".to_string(), Value::Text("M{\\\"u}ller, J{\\\"o}rg and {World Health Organization}".to_string())),
+            ("title".to_string(), Value::Protected("Caf{\\'e} {DNA}".to_string())),
+            ("journal".to_string(), Value::Macro("jcomp".to_string())),
+            ("month".to_string(), Value::Macro("jun # \"/\" # jul".to_string()))
+        ];
+        for quotes in [false, true] {
+            let format = Format { quotes, ..Format::default() };
+            let mut written = Vec::new();
+            printer::write_entry(&mut written, "article", "a", &fields, &format).unwrap();
+            let entry = &parse_entries(&String::from_utf8(written).unwrap())[0];
+
+            assert_eq!(entry.fields.len(), fields.len());
+            for (name, value) in &fields {
+                assert_eq!(entry.get(name), Some(value.as_str()), "{} with quotes {}", name, quotes);
+            }
+            assert_eq!(entry.macros, ["journal", "month"]);
+        }
+    }
+}
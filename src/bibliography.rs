@@ -0,0 +1,346 @@
+use std::{collections::{HashMap, HashSet}, io::{self, Write}};
+use colored::Colorize;
+
+use crate::bibfile::{self, Block, RawEntry};
+use crate::case;
+use crate::constants;
+use crate::crossref::{self, Resolved};
+use crate::dedup::{self, DedupMode};
+use crate::dialect::{self, Dialect};
+use crate::diff;
+use crate::entry::{self, Entry};
+use crate::escape;
+use crate::keygen;
+use crate::names::{self, FirstNames, Initials};
+use crate::options::Options;
+use crate::printer::{self, Field, Value};
+use crate::protect::{self, Protection};
+
+/// An entry of the bibliography together with everything needed to write it back.
+/// Parents of unsupported types (e.g. @proceedings) have no `entry` and are written from
+/// their fields as read, `@string`, `@preamble` and `@comment` blocks are copied verbatim;
+/// entries that failed validation or have an unknown type carry the reason in `error`.
+pub struct Record {
+    pub raw: RawEntry,
+    pub resolved: Resolved,
+    pub entry: Option<Entry>,
+    pub key: String,
+    pub error: Option<String>
+}
+
+pub fn process(input: &str, options: &Options) -> Vec<Record> {
+    process_entries(bibfile::parse_entries(input), options)
+}
+
+/// Deduplicates, resolves and validates every entry, then assigns unique keys and sorts.
+pub fn process_entries(raws: Vec<RawEntry>, options: &Options) -> Vec<Record> {
+    let dialect = options.dialect.unwrap_or(Dialect::BibTeX);
+    let (specials, mut raws): (Vec<RawEntry>, Vec<RawEntry>) = raws.into_iter().partition(RawEntry::is_special);
+    if let Some(mode) = options.dedup {
+        let duplicates = dedup::find_duplicates(&raws);
+        dedup::report(&raws, &duplicates);
+        if mode == DedupMode::Merge {
+            raws = dedup::merge(raws, &duplicates);
+        }
+    }
+    let resolved = crossref::resolve(&raws);
+    let parents = crossref::parents(&raws);
+    let mut records = Vec::new();
+
+    for (raw, resolved) in raws.into_iter().zip(resolved) {
+        let supported = entry::SUPPORTED_TYPES.contains(&raw.entry_type.as_str()) || raw.entry_type == "online";
+        let key = raw.key.clone();
+        if !supported && parents.contains(&raw.key) {
+            records.push(Record { raw, resolved, entry: None, key, error: None });
+            continue;
+        }
+        if !supported {
+            eprintln!("{} Unknown entry type: {}", constants::INFO.blue(), raw.entry_type);
+            let error = format!("Unknown entry type: {}", raw.entry_type);
+            records.push(Record { raw, resolved, entry: None, key, error: Some(error) });
+            continue;
+        }
+
+        // Reviewed under the names they were read with, before a date becomes a month macro.
+        let mut fields = resolved.fields.clone();
+        escape::review(&raw.key, &mut fields, &resolved.macros, options.fix_escapes);
+        let entry_type = dialect::normalize_input(&raw.entry_type, &raw.key, &mut fields, dialect);
+
+        match Entry::new(&entry_type, &fields) {
+            Ok(entry) => {
+                let key = if options.key_policy.should_regenerate(&raw.key) {
+                    entry.generate_key(&options.key_pattern)
+                } else {
+                    key
+                };
+                records.push(Record { raw, resolved, entry: Some(entry), key, error: None });
+            }
+            Err(e) => {
+                eprintln!("{} Error processing {} entry {}: {}", constants::ERR.red(), raw.entry_type, raw.key.red(), e);
+                records.push(Record { raw, resolved, entry: None, key, error: Some(e) });
+            }
+        }
+    }
+
+    report_duplicate_keys(&records);
+    let mut keys: Vec<String> = records.iter().map(|r| r.key.clone()).collect();
+    let titles: Vec<String> = records.iter().map(|r| r.resolved.fields.get("title").cloned().unwrap_or_default()).collect();
+    keygen::disambiguate(&mut keys, &titles);
+    for (record, key) in records.iter_mut().zip(keys) {
+        record.key = key;
+    }
+    rename_links(&mut records);
+
+    if let Some(order) = &options.sort {
+        if options.lossless {
+            eprintln!("{} Sorting is ignored in lossless mode", constants::WARN.yellow());
+        } else {
+            order.apply(&mut records, options.reverse);
+        }
+    }
+
+    // @string, @preamble and @comment blocks go first, so that macros are defined before use.
+    let mut blocks: Vec<Record> = Vec::new();
+    for raw in specials {
+        if blocks.iter().any(|block| block.raw.text == raw.text) {
+            continue;
+        }
+        let resolved = Resolved { fields: raw.field_map(), inherited: Vec::new(), links: Vec::new(), macros: raw.macros.clone() };
+        let key = raw.key.clone();
+        blocks.push(Record { raw, resolved, entry: None, key, error: None });
+    }
+    blocks.extend(records);
+
+    blocks
+}
+
+/// What to do with entries that failed validation or have an unknown type.
+#[derive(Clone, Copy, PartialEq)]
+pub enum InvalidPolicy {
+    Drop,
+    Keep,
+    Comment
+}
+
+impl InvalidPolicy {
+    pub fn parse(name: &str) -> Result<InvalidPolicy, String> {
+        match name {
+            "drop" => Ok(InvalidPolicy::Drop),
+            "keep" => Ok(InvalidPolicy::Keep),
+            "comment" => Ok(InvalidPolicy::Comment),
+            _ => Err(format!("Unknown invalid entry policy: {} (expected drop, keep or comment)", name)),
+        }
+    }
+}
+
+pub fn write<'a, W: Write, I: IntoIterator<Item = &'a Record>>(writer: &mut W, records: I, options: &Options) -> io::Result<()> {
+    let policy = options.invalid.unwrap_or(InvalidPolicy::Drop);
+    let records: Vec<&Record> = records.into_iter().collect();
+    let first_names = first_names(records.iter().copied(), options);
+
+    for record in records {
+        if let Some(error) = &record.error {
+            write_invalid(writer, record, error, policy)?;
+            if policy != InvalidPolicy::Drop {
+                writeln!(writer)?;
+            }
+            continue;
+        }
+        if !record.raw.sources.is_empty() {
+            writeln!(writer, "% from {}", record.raw.sources.join(", "))?;
+        }
+        match &record.entry {
+            None if record.raw.is_special() => writeln!(writer, "{}", record.raw.text)?,
+            Some(entry) => {
+                let (entry_type, fields) = output_fields(record, entry, options, &first_names);
+                printer::write_entry(writer, &entry_type, &record.key, &fields, &options.format)?;
+            }
+            None if !options.inline_crossref => {
+                let dialect = options.dialect.unwrap_or(Dialect::BibTeX);
+                let (entry_type, fields) = dialect::map_raw(&record.raw.entry_type, printer::raw_fields(&record.raw), dialect);
+                printer::write_entry(writer, &entry_type, &record.key, &fields, &options.format)?;
+            }
+            None => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the document back block by block: text between entries, unknown blocks,
+/// parents and invalid entries are kept as read, and valid entries are only rewritten
+/// when formatting changes their type, key or field values.
+pub fn write_lossless<W: Write>(writer: &mut W, blocks: &[Block], records: &[Record], options: &Options) -> io::Result<()> {
+    let by_offset: HashMap<usize, &Record> = records.iter().map(|r| (r.raw.offset, r)).collect();
+    let mut previous_text = "";
+    let first_names = first_names(records, options);
+
+    for block in blocks {
+        let raw = match block {
+            Block::Text(text) => {
+                write!(writer, "{}", text)?;
+                previous_text = text;
+                continue;
+            }
+            Block::Entry(raw) => raw,
+        };
+        let Some(record) = by_offset.get(&raw.offset) else { continue };
+        if let Some(error) = &record.error {
+            let mut policy = options.invalid.unwrap_or(InvalidPolicy::Keep);
+            if policy == InvalidPolicy::Comment && previous_text.ends_with(&format!("{}\n", error_comment(error))) {
+                policy = InvalidPolicy::Keep;
+            }
+            write_invalid(writer, record, error, policy)?;
+            previous_text = "";
+            continue;
+        }
+        previous_text = "";
+        let Some(entry) = &record.entry else {
+            write!(writer, "{}", record.raw.text)?;
+            continue;
+        };
+
+        let (entry_type, mut fields) = output_fields(record, entry, options, &first_names);
+        let mut known: Vec<String> = fields.iter().map(|(name, _)| name.to_string()).collect();
+        known.extend(entry.fields().into_iter().map(|(name, _)| name));
+        // Fields the structs do not model are written back exactly as read.
+        fields.extend(record.raw.fields.iter()
+            .filter(|(name, _)| !known.contains(name) && !dialect::BIBLATEX_ALIASES.contains(&name.as_str()) && !crossref::is_link(name))
+            .filter_map(|(name, _)| printer::verbatim(&record.raw, name).map(|value| (name.to_string(), value))));
+
+        let mut rendered = Vec::new();
+        printer::write_entry(&mut rendered, &entry_type, &record.key, &fields, &options.format)?;
+        let rendered = String::from_utf8_lossy(&rendered);
+        let rendered = rendered.trim_end();
+
+        if same_entry(&record.raw, rendered) {
+            write!(writer, "{}", record.raw.text)?;
+        } else {
+            write!(writer, "{}", rendered)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes a failed entry verbatim, optionally preceded by a `% bibliohelp: error` comment.
+fn write_invalid<W: Write>(writer: &mut W, record: &Record, error: &str, policy: InvalidPolicy) -> io::Result<()> {
+    match policy {
+        InvalidPolicy::Drop => Ok(()),
+        InvalidPolicy::Keep => write!(writer, "{}", record.raw.text),
+        InvalidPolicy::Comment => {
+            writeln!(writer, "{}", error_comment(error))?;
+            write!(writer, "{}", record.raw.text)
+        }
+    }
+}
+
+fn error_comment(error: &str) -> String {
+    format!("% bibliohelp: error {}", plain_message(error))
+}
+
+/// Error message without terminal colors and without the leading [ERR] tag.
+fn plain_message(error: &str) -> String {
+    let mut plain = String::new();
+    let mut chars = error.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|&c| c == 'm');
+        } else {
+            plain.push(c);
+        }
+    }
+    plain.trim().trim_start_matches(constants::ERR).trim().to_string()
+}
+
+fn output_fields(record: &Record, entry: &Entry, options: &Options, first_names: &FirstNames) -> (String, Vec<Field>) {
+    let dialect = options.dialect.unwrap_or(Dialect::BibTeX);
+    let crossref = if options.inline_crossref { None } else { Some(&record.resolved) };
+    let (entry_type, mut fields) = entry.output(dialect, crossref);
+    for (name, value) in fields.iter_mut() {
+        // A value read as a macro reference, such as `journal = jcomp`, is written as one.
+        let is_macro = record.raw.macros.iter().any(|raw_name| dialect::same_field(raw_name, name) && record.raw.get(raw_name) == Some(value.as_str()));
+        if is_macro {
+            *value = Value::Macro(value.as_str().to_string());
+            continue;
+        }
+        match value {
+            Value::Text(list) if name == "author" || name == "editor" => {
+                *list = names::restyle(list, options.name_style, options.initials, first_names);
+            }
+            Value::Protected(title) if name == "title" || name == "booktitle" => {
+                if let Some(title_case) = options.title_case {
+                    *title = case::convert(title, title_case, &options.case_rules, &options.protect_words);
+                }
+                if options.protection != Protection::All {
+                    *value = Value::Text(protect::apply(title, options.protection, &options.protect_words, name, &record.key));
+                }
+            }
+            _ => {}
+        }
+    }
+    (entry_type, fields)
+}
+
+/// Full first names of every valid entry, only gathered when initials are to be expanded.
+fn first_names<'a, I: IntoIterator<Item = &'a Record>>(records: I, options: &Options) -> FirstNames {
+    let mut lists = Vec::new();
+    if options.initials == Initials::Expand {
+        for entry in records.into_iter().filter_map(|record| record.entry.as_ref()) {
+            lists.extend(entry.fields().into_iter().filter(|(name, _)| name == "author" || name == "editor").map(|(_, value)| value.as_str().to_string()));
+        }
+    }
+    FirstNames::collect(lists.iter().map(String::as_str))
+}
+
+fn same_entry(raw: &RawEntry, rendered: &str) -> bool {
+    let Some(new) = bibfile::parse_entries(rendered).into_iter().next() else { return false };
+    new.entry_type == raw.entry_type
+        && new.key == raw.key
+        && new.fields.len() == raw.fields.len()
+        && raw.fields.iter().all(|(name, value)| new.get(name).map(diff::normalize_value) == Some(diff::normalize_value(value)))
+}
+
+/// Maps old keys to the new ones, and lists the keys used by several input entries, which
+/// are ambiguous and left out of the map.
+pub fn key_map(records: &[Record]) -> (HashMap<String, String>, Vec<String>) {
+    let mut renames = HashMap::new();
+    let mut ambiguous = Vec::new();
+
+    for record in records {
+        if renames.insert(record.raw.key.clone(), record.key.clone()).is_some() && !ambiguous.contains(&record.raw.key) {
+            ambiguous.push(record.raw.key.clone());
+        }
+    }
+    for key in &ambiguous {
+        renames.remove(key);
+    }
+    renames.retain(|old, new| old != new);
+
+    (renames, ambiguous)
+}
+
+/// Points the crossref and xdata links of every entry to the new keys of their parents.
+fn rename_links(records: &mut [Record]) {
+    let (renames, _) = key_map(records);
+    if renames.is_empty() {
+        return;
+    }
+    for record in records.iter_mut() {
+        for (_, value) in record.resolved.links.iter_mut() {
+            *value = value.split(',')
+                .map(|key| renames.get(key.trim()).map(String::as_str).unwrap_or(key.trim()))
+                .collect::<Vec<&str>>()
+                .join(",");
+        }
+    }
+}
+
+fn report_duplicate_keys(records: &[Record]) {
+    let mut seen = HashSet::new();
+    for record in records {
+        if !seen.insert(record.raw.key.as_str()) {
+            eprintln!("{} Duplicate key {} in input", constants::WARN.yellow(), record.raw.key);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use crate::protect;
+
+/// Words kept in lowercase inside a title in Title Case.
+pub const SMALL_WORDS: [&str; 22] = [
+    "a", "an", "the", "and", "but", "or", "nor", "for", "so", "yet", "as", "at",
+    "by", "in", "of", "off", "on", "per", "to", "up", "via", "vs"
+];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TitleCase {
+    Title,
+    Sentence
+}
+
+impl TitleCase {
+    pub fn parse(name: &str) -> Result<TitleCase, String> {
+        match name {
+            "title" => Ok(TitleCase::Title),
+            "sentence" => Ok(TitleCase::Sentence),
+            _ => Err(format!("Unknown title case: {} (expected title or sentence)", name)),
+        }
+    }
+}
+
+/// Rules of the `--title-case` conversion.
+pub struct CaseRules {
+    pub small_words: Vec<String>,
+    pub hyphenated_all: bool,
+    pub after_colon: bool
+}
+
+impl Default for CaseRules {
+    fn default() -> CaseRules {
+        CaseRules {
+            small_words: SMALL_WORDS.iter().map(|word| word.to_string()).collect(),
+            hyphenated_all: true,
+            after_colon: true
+        }
+    }
+}
+
+impl CaseRules {
+    /// Applies one of the `--small-words`, `--hyphenated` or `--after-colon` options.
+    pub fn set(&mut self, option: &str, value: &str) -> Result<(), String> {
+        match option {
+            "--small-words" => {
+                self.small_words = value.split(',').map(|word| word.trim().to_lowercase()).filter(|word| !word.is_empty()).collect();
+            }
+            "--hyphenated" => {
+                self.hyphenated_all = match value {
+                    "all" => true,
+                    "first" => false,
+                    _ => return Err(format!("Invalid hyphenated compound rule: {} (expected all or first)", value)),
+                };
+            }
+            "--after-colon" => {
+                self.after_colon = match value {
+                    "capitalize" => true,
+                    "keep" => false,
+                    _ => return Err(format!("Invalid after colon rule: {} (expected capitalize or keep)", value)),
+                };
+            }
+            _ => return Err(format!("Unknown option: {}", option)),
+        }
+        Ok(())
+    }
+}
+
+/// Converts the words of `title` to Title Case or sentence case. Braced groups, math,
+/// commands, acronyms and mixed-case words (DNA, iPhone) and the words of the project
+/// list are left as they are, and so is the word after a colon unless it is to be capitalised.
+pub fn convert(title: &str, case: TitleCase, rules: &CaseRules, keep: &[String]) -> String {
+    let mut converted = String::new();
+    let mut written = 0;
+
+    for (start, end) in protect::bare_words(title) {
+        let word = &title[start..end];
+        if word.chars().skip(1).any(char::is_uppercase) || word == "I" || keep.contains(&word.to_lowercase()) {
+            continue;
+        }
+        let before = title[..start].trim_end();
+        let compound = title[..start].ends_with('-');
+        let after_colon = !compound && before.ends_with([':', '.', '?', '!']);
+        if after_colon && !rules.after_colon {
+            continue;
+        }
+        let opening = !before.chars().any(char::is_alphanumeric) || after_colon;
+        let lower = word.to_lowercase();
+
+        let new_word = match case {
+            _ if opening => capitalize(word),
+            TitleCase::Sentence => lower,
+            TitleCase::Title if compound && !rules.hyphenated_all => lower,
+            TitleCase::Title if !title[end..].chars().any(char::is_alphanumeric) => capitalize(word),
+            TitleCase::Title if rules.small_words.contains(&lower) => lower,
+            TitleCase::Title => capitalize(word),
+        };
+        converted.push_str(&title[written..start]);
+        converted.push_str(&new_word);
+        written = end;
+    }
+    converted.push_str(&title[written..]);
+
+    converted
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn title(text: &str) -> String {
+        convert(text, TitleCase::Title, &CaseRules::default(), &[])
+    }
+
+    #[test]
+    fn small_words() {
+        assert_eq!(title("the art of computer programming"), "The Art of Computer Programming");
+        assert_eq!(title("a study in scarlet and gold"), "A Study in Scarlet and Gold");
+        // The last word is capitalised even when it is a small word.
+        assert_eq!(title("what to look for"), "What to Look For");
+    }
+
+    #[test]
+    fn hyphenated_compounds() {
+        assert_eq!(title("a well-known result"), "A Well-Known Result");
+
+        let rules = CaseRules { hyphenated_all: false, ..CaseRules::default() };
+        assert_eq!(convert("a well-known result", TitleCase::Title, &rules, &[]), "A Well-known Result");
+    }
+
+    #[test]
+    fn after_colon() {
+        assert_eq!(title("graphs: a survey"), "Graphs: A Survey");
+
+        let rules = CaseRules { after_colon: false, ..CaseRules::default() };
+        assert_eq!(convert("graphs: a survey", TitleCase::Title, &rules, &[]), "Graphs: a Survey");
+        assert_eq!(convert("Graphs: A survey", TitleCase::Sentence, &rules, &[]), "Graphs: A survey");
+    }
+
+    #[test]
+    fn protected_words() {
+        assert_eq!(title("on {the} use of {LaTeX} in {b}iology"), "On {the} Use of {LaTeX} in {b}iology");
+        assert_eq!(title("bounds on $o(n)$ and more"), "Bounds on $o(n)$ and More");
+        assert_eq!(title("sequencing DNA on the iPhone"), "Sequencing DNA on the iPhone");
+    }
+
+    #[test]
+    fn project_words() {
+        let keep = vec!["bibtex".to_string()];
+        assert_eq!(convert("tools for bibtex files", TitleCase::Title, &CaseRules::default(), &keep), "Tools for bibtex Files");
+        assert_eq!(convert("Tools For bibtex Files", TitleCase::Sentence, &CaseRules::default(), &keep), "Tools for bibtex files");
+    }
+
+    #[test]
+    fn sentence_case() {
+        let rules = CaseRules::default();
+        assert_eq!(convert("The Art Of Programming", TitleCase::Sentence, &rules, &[]), "The art of programming");
+        assert_eq!(convert("Graphs: A Survey", TitleCase::Sentence, &rules, &[]), "Graphs: A survey");
+    }
+}
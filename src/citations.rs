@@ -0,0 +1,71 @@
+use std::{collections::HashMap, fs, io, path::Path};
+use regex::{Captures, Regex};
+
+use crate::constants;
+
+/// Keys cited by a LaTeX project, read from `.aux` \citation lines, biber `.bcf`
+/// citekeys or \cite-like commands in `.tex` sources. Each key appears once, in citation order.
+pub fn collect(files: &[String]) -> io::Result<Vec<String>> {
+    let mut keys: Vec<String> = Vec::new();
+
+    for file in files {
+        let content = fs::read_to_string(file)?;
+        let found = match Path::new(file).extension().and_then(|e| e.to_str()) {
+            Some("aux") => captured_keys(constants::REGEX_AUX_CITATION, &content),
+            Some("bcf") => captured_keys(constants::REGEX_BCF_CITEKEY, &content),
+            _ => cited_keys(&content),
+        };
+        for key in found {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+
+    Ok(keys)
+}
+
+/// Keys cited by \cite-like commands (\cite, \citep, \citet, \autocite, \nocite, ...) in a LaTeX source.
+pub fn cited_keys(tex: &str) -> Vec<String> {
+    let re = Regex::new(constants::REGEX_CITE).unwrap();
+    let mut keys = Vec::new();
+
+    for cap in re.captures_iter(tex) {
+        keys.extend(cap[2].split(',').map(str::trim).filter(|k| !k.is_empty()).map(str::to_string));
+    }
+
+    keys
+}
+
+fn captured_keys(pattern: &str, content: &str) -> Vec<String> {
+    let re = Regex::new(pattern).unwrap();
+    let mut keys = Vec::new();
+
+    for cap in re.captures_iter(content) {
+        keys.extend(cap[1].split(',').map(str::trim).filter(|k| !k.is_empty()).map(str::to_string));
+    }
+
+    keys
+}
+
+/// Rewrites the keys of every citation command according to `renames`, returning the
+/// new source and the number of keys that were changed.
+pub fn rename_keys(tex: &str, renames: &HashMap<String, String>) -> (String, usize) {
+    let re = Regex::new(constants::REGEX_CITE).unwrap();
+    let mut count = 0;
+
+    let rewritten = re.replace_all(tex, |cap: &Captures| {
+        let keys: Vec<String> = cap[2].split(',').map(|key| {
+            match renames.get(key.trim()) {
+                Some(new_key) => {
+                    count += 1;
+                    key.replace(key.trim(), new_key)
+                }
+                None => key.to_string(),
+            }
+        }).collect();
+        format!("{}{}}}", &cap[1], keys.join(","))
+    });
+
+    (rewritten.into_owned(), count)
+}
@@ -1,15 +1,18 @@
-use std::{collections::HashMap, io::{self, Write}};
+use std::collections::HashMap;
 use regex::Regex;
 use colored::Colorize;
 
 use crate::constants;
+use crate::names::{self, Name};
+use crate::printer::{Field, Value};
+use crate::tex;
 
 
 pub struct Collection {
-    author: String,
+    author: Vec<Name>,
     title: String,
     booktitle: String,
-    editor: String,
+    editor: Vec<Name>,
     publisher: String,
     year: i32,
     pages: String,
@@ -18,14 +21,7 @@ pub struct Collection {
 }
 
 impl Collection {
-    pub fn new(input: &str) -> Result<Collection, String> {
-        let re = Regex::new(constants::REGEX_ENTRY).unwrap();
-        let mut fields: HashMap<String, String> = HashMap::new();
-
-        for cap in re.captures_iter(input) {
-            fields.insert(cap[1].to_string(), cap[2].trim_matches('{').trim_matches('}').to_string());
-        }
-
+    pub fn new(fields: &HashMap<String, String>) -> Result<Collection, String> {
         let author = fields.get("author").and_then(|s| if !s.is_empty() { Some(s) } else { None }).ok_or("Missing or empty author field")?.to_string();
         let title = fields.get("title").and_then(|s| if !s.is_empty() { Some(s) } else { None }).ok_or("Missing or empty title field")?.to_string();
         let booktitle = fields.get("booktitle").and_then(|s| if !s.is_empty() { Some(s) } else { None }).ok_or("Missing or empty booktitle field")?.to_string();
@@ -40,32 +36,26 @@ impl Collection {
         let isbn = fields.get("isbn").and_then(|s| if !s.is_empty() { Some(s) } else { None }).ok_or("Missing or empty isbn field")?.to_string();
         let doi = fields.get("doi").and_then(|s| if !s.is_empty() { Some(s) } else { None }).ok_or("Missing or empty doi field")?.to_string();
 
-        let author_re = Regex::new(constants::REGEX_AUTHOR).unwrap();
         let title_re = Regex::new(constants::REGEX_TITLE).unwrap();
         let booktitle_re = Regex::new(constants::REGEX_TITLE).unwrap();
-        let editor_re = Regex::new(constants::REGEX_TITLE).unwrap();
         let publisher_re = Regex::new(constants::REGEX_TITLE).unwrap();
         let pages_re = Regex::new(constants::REGEX_PAGES).unwrap();
         let isbn_re = Regex::new(constants::REGEX_ISBN).unwrap();
         let doi_re = Regex::new(constants::REGEX_DOI).unwrap();
 
-        if !author_re.is_match(&author) {
-            return Err(format!("{} Invalid authors format", constants::ERR.red()));
-        }
+        let author = names::parse_list(&author).map_err(|e| format!("{} Invalid authors format: {}", constants::ERR.red(), e))?;
 
-        if !title_re.is_match(&title) {
+        if !title_re.is_match(&tex::to_plain(&title)) {
             return Err(format!("{} Invalid title format", constants::ERR.red()));
         }
 
-        if !booktitle_re.is_match(&booktitle) {
+        if !booktitle_re.is_match(&tex::to_plain(&booktitle)) {
             return Err(format!("{} Invalid booktitle format", constants::ERR.red()));
         }
 
-        if !editor_re.is_match(&editor) {
-            return Err(format!("{} Invalid editor format", constants::ERR.red()));
-        }
+        let editor = names::parse_list(&editor).map_err(|e| format!("{} Invalid editor format: {}", constants::ERR.red(), e))?;
 
-        if !publisher_re.is_match(&publisher) {
+        if !publisher_re.is_match(&tex::to_plain(&publisher)) {
             return Err(format!("{} Invalid publisher format", constants::ERR.red()));
         }
 
@@ -99,23 +89,17 @@ impl Collection {
         })
     }
 
-    pub fn print<W: Write>(&self, writer: &mut W) -> io::Result<()> {
-        writeln!(writer, "@inproceedings{{{},", self.generate_key())?;
-        writeln!(writer, "    author         = {{{}}},", self.author)?;
-        writeln!(writer, "    title          = {{{{{}}}}},", self.title)?;
-        writeln!(writer, "    booktitle      = {{{{{}}}}},", self.booktitle)?;
-        writeln!(writer, "    editor         = {{{}}},", self.editor)?;
-        writeln!(writer, "    publisher      = {{{}}},", self.publisher)?;
-        writeln!(writer, "    year           = {{{}}},", self.year)?;
-        writeln!(writer, "    pages          = {{{}}},", self.pages)?;
-        writeln!(writer, "    isbn           = {{{}}},", self.isbn)?;
-        writeln!(writer, "    doi            = {{{}}}", self.doi)?;
-        writeln!(writer, "}}")?;
-        Ok(())
-    }
-
-    fn generate_key(&self) -> String {
-        let first_author_last_name = self.author.split(',').next().unwrap_or("").trim();
-        format!("{}{}", first_author_last_name.to_lowercase(), self.year)
+    pub fn fields(&self) -> Vec<Field> {
+        vec![
+            ("author".to_string(), Value::Text(names::format_list(&self.author))),
+            ("title".to_string(), Value::Protected(self.title.clone())),
+            ("booktitle".to_string(), Value::Protected(self.booktitle.clone())),
+            ("editor".to_string(), Value::Text(names::format_list(&self.editor))),
+            ("publisher".to_string(), Value::Text(self.publisher.clone())),
+            ("year".to_string(), Value::Text(self.year.to_string())),
+            ("pages".to_string(), Value::Text(self.pages.clone())),
+            ("isbn".to_string(), Value::Text(self.isbn.clone())),
+            ("doi".to_string(), Value::Text(self.doi.clone())),
+        ]
     }
 }
\ No newline at end of file
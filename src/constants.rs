@@ -1,12 +1,16 @@
-pub const REGEX_ENTRY: &str = r"(?m)^\s*(\w+)\s*=\s*(\{{0,2}[^{}]*\}{0,2}|[^,]+),?\s*$"; 
+pub const REGEX_ENTRY: &str = r#"(?m)^\s*(\w+)\s*=\s*(\{(?:[^{}]|\{(?:[^{}]|\{[^{}]*\})*\})*\}|"(?:[^"{}]|\{(?:[^{}]|\{(?:[^{}]|\{[^{}]*\})*\})*\})*"|[^,\n]+),?\s*$"#;
 
-pub const REGEX_AUTHOR: &str = r"(?:[A-Za-z\.'-]+(?: [A-Za-z\.'-]+)?, ?[A-Za-z\.'-]+(?: [A-Za-z\.'-]+)?) (?:and [A-Za-z\.'-]+(?: [A-Za-z\.'-]+)?, ?[A-Za-z\.'-]+(?: [A-Za-z\.'-]+)?)*(?: and [A-Za-z\.'-]+(?: [A-Za-z\.'-]+)?, ?[A-Za-z\.'-]+(?: [A-Za-z\.'-]+)?)?|(?:[A-Za-z\.'-]+(?: [A-Za-z\.'-]+)?, ?[A-Za-z\.'-]+(?: [A-Za-z\.'-]+)?)";
-pub const REGEX_TITLE: &str = r"^[a-zA-Z0-9\s,;?!.:()-_]+$";
-pub const REGEX_ADDRESS: &str = r"^[A-Za-z\s]+ \([A-Za-z\s]+(?:, [A-Za-z\s]+)?\)$";
+pub const REGEX_NAME: &str = r"^[\p{L}\s.,'\-~]+$";
+pub const REGEX_TITLE: &str = r"^[\p{L}\p{N}\s,;?!.:()-_'`–—“”‘’&%#$]+$";
+pub const REGEX_ADDRESS: &str = r"^[\p{L}\s]+ \([\p{L}\s]+(?:, [\p{L}\s]+)?\)$";
 pub const REGEX_PAGES: &str = r"^\d+-\d+$";
 pub const REGEX_DOI: &str = r"^10\.\d+\/[-._;()\/:A-Za-z0-9\.]+$";
-pub const REGEX_MONTH: &str = r"^(jan|feb|mar|apr|may|jun|jul|aug|sep|oct|nov|dec)$";
 pub const REGEX_ISBN: &str = r"^[\d-]+$";
+pub const REGEX_AUX_CITATION: &str = r"\\citation\{([^}]*)\}";
+pub const REGEX_BCF_CITEKEY: &str = r"<bcf:citekey[^>]*>([^<]*)</bcf:citekey>";
+pub const REGEX_CITE: &str = r"(\\[A-Za-z]*cite[A-Za-z]*\*?(?:\s*\[[^\]]*\]){0,2}\s*\{)([^}]*)\}";
+
+pub const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
 
 pub const ERR: &str = "[ERR]";
 pub const WARN: &str = "[WARN]";
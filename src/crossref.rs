@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use colored::Colorize;
+
+use crate::bibfile::RawEntry;
+use crate::constants;
+use crate::dialect::{self, Dialect};
+
+/// Fields of an entry after crossref and xdata inheritance. `inherited` pairs the name of
+/// each inherited field in the child with its name in the parent, and `macros` lists the
+/// fields written without delimiters in the entry that holds them.
+pub struct Resolved {
+    pub fields: HashMap<String, String>,
+    pub inherited: Vec<(String, String)>,
+    pub links: Vec<(String, String)>,
+    pub macros: Vec<String>
+}
+
+impl Resolved {
+    /// Whether the child can leave out its field `name` because the parent carries it once
+    /// written for `dialect`. Fields taken under another name, such as the booktitle made from
+    /// the parent's title, are only inherited that way by biblatex.
+    pub fn is_inherited(&self, name: &str, dialect: Dialect) -> bool {
+        self.inherited.iter().any(|(field, parent_field)| {
+            dialect::same_field(field, name) && (field == parent_field || dialect == Dialect::BibLaTeX)
+        })
+    }
+}
+
+/// Resolves `crossref` and `xdata` parents for every entry, reporting dangling keys
+/// and parents that appear after their children.
+pub fn resolve(entries: &[RawEntry]) -> Vec<Resolved> {
+    let positions: HashMap<&str, usize> = entries.iter().enumerate().map(|(i, e)| (e.key.as_str(), i)).collect();
+
+    entries.iter().enumerate().map(|(i, entry)| {
+        let mut fields = entry.field_map();
+        let mut inherited = Vec::new();
+        let links: Vec<(String, String)> = entry.fields.iter().filter(|(name, _)| is_link(name)).cloned().collect();
+        for (name, _) in &links {
+            fields.remove(name);
+        }
+        let mut macros = entry.macros.clone();
+        let mut visited = vec![i];
+        inherit(entries, &positions, i, &mut fields, &mut inherited, &mut macros, &mut visited);
+        Resolved { fields, inherited, links, macros }
+    }).collect()
+}
+
+/// Keys of the entries that other entries point to through crossref or xdata.
+pub fn parents(entries: &[RawEntry]) -> Vec<String> {
+    let mut parents = Vec::new();
+    for entry in entries {
+        for (name, value) in &entry.fields {
+            if is_link(name) {
+                parents.extend(value.split(',').map(|key| key.trim().to_string()));
+            }
+        }
+    }
+    parents
+}
+
+fn inherit(entries: &[RawEntry], positions: &HashMap<&str, usize>, index: usize, fields: &mut HashMap<String, String>,
+           inherited: &mut Vec<(String, String)>, macros: &mut Vec<String>, visited: &mut Vec<usize>) {
+    let child = &entries[index];
+
+    for (name, value) in &child.fields {
+        if !is_link(name) {
+            continue;
+        }
+
+        for key in value.split(',').map(str::trim).filter(|k| !k.is_empty()) {
+            let parent_index = match positions.get(key) {
+                Some(&p) => p,
+                None => {
+                    eprintln!("{} Dangling {} {} in entry {}", constants::WARN.yellow(), name, key.red(), child.key);
+                    continue;
+                }
+            };
+            if visited.contains(&parent_index) {
+                eprintln!("{} Circular {} {} in entry {}", constants::WARN.yellow(), name, key, child.key);
+                continue;
+            }
+            if parent_index > index {
+                eprintln!("{} Parent {} appears after child {}", constants::WARN.yellow(), key, child.key);
+            }
+
+            let parent = &entries[parent_index];
+            for (parent_name, parent_value) in &parent.fields {
+                let target = match parent_name.as_str() {
+                    "crossref" | "xdata" => continue,
+                    "title" if name == "crossref" && matches!(parent.entry_type.as_str(), "proceedings" | "book") => "booktitle",
+                    "title" if name == "crossref" => continue,
+                    other => other,
+                };
+                // The child may hold the same data under the name of the other dialect.
+                if !fields.keys().any(|name| dialect::same_field(name, target)) {
+                    fields.insert(target.to_string(), parent_value.to_string());
+                    inherited.push((target.to_string(), parent_name.to_string()));
+                    if parent.macros.contains(parent_name) {
+                        macros.push(target.to_string());
+                    }
+                }
+            }
+
+            visited.push(parent_index);
+            inherit(entries, positions, parent_index, fields, inherited, macros, visited);
+        }
+    }
+}
+
+pub fn is_link(name: &str) -> bool {
+    name == "crossref" || name == "xdata"
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+use colored::Colorize;
+
+use crate::bibfile::RawEntry;
+use crate::constants;
+use crate::names;
+use crate::similarity;
+
+/// Smallest title similarity for two entries with the same year and first author to be duplicates.
+const MIN_TITLE_RATIO: f64 = 0.9;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DedupMode {
+    Report,
+    Merge
+}
+
+impl DedupMode {
+    pub fn parse(name: &str) -> Result<DedupMode, String> {
+        match name {
+            "report" => Ok(DedupMode::Report),
+            "merge" => Ok(DedupMode::Merge),
+            _ => Err(format!("Unknown dedup mode: {} (expected report or merge)", name)),
+        }
+    }
+}
+
+pub struct Duplicate {
+    pub first: usize,
+    pub second: usize,
+    pub reason: String
+}
+
+/// Finds pairs of entries describing the same work: same normalised DOI or ISBN, or
+/// same year and first author with nearly identical titles.
+pub fn find_duplicates(entries: &[RawEntry]) -> Vec<Duplicate> {
+    let mut duplicates = Vec::new();
+    let mut by_doi: HashMap<String, usize> = HashMap::new();
+    let mut by_isbn: HashMap<String, usize> = HashMap::new();
+    let mut by_author_year: HashMap<(String, String), Vec<usize>> = HashMap::new();
+
+    for (i, entry) in entries.iter().enumerate() {
+        let doi = entry.get("doi").map(normalize_doi).unwrap_or_default();
+        let isbn = entry.get("isbn").map(normalize_isbn).unwrap_or_default();
+
+        if let Some(&first) = by_doi.get(&doi).filter(|_| !doi.is_empty()) {
+            duplicates.push(Duplicate { first, second: i, reason: format!("same DOI {}", doi) });
+            continue;
+        }
+        if let Some(&first) = by_isbn.get(&isbn).filter(|_| !isbn.is_empty()) {
+            duplicates.push(Duplicate { first, second: i, reason: format!("same ISBN {}", isbn) });
+            continue;
+        }
+        if !doi.is_empty() {
+            by_doi.insert(doi, i);
+        }
+        if !isbn.is_empty() {
+            by_isbn.insert(isbn, i);
+        }
+
+        let title = normalize_title(entry.get("title").unwrap_or(""));
+        let bucket = by_author_year.entry((first_author(entry), entry.get("year").unwrap_or("").to_string())).or_default();
+        let similar = bucket.iter().copied().find(|&j| {
+            similarity::ratio(&title, &normalize_title(entries[j].get("title").unwrap_or(""))) >= MIN_TITLE_RATIO
+        });
+        match similar {
+            Some(first) if !title.is_empty() => {
+                duplicates.push(Duplicate { first, second: i, reason: "similar title, year and first author".to_string() });
+            }
+            _ => bucket.push(i),
+        }
+    }
+
+    duplicates
+}
+
+pub fn report(entries: &[RawEntry], duplicates: &[Duplicate]) {
+    for duplicate in duplicates {
+        eprintln!("{} Entries {} and {} look like duplicates ({})", constants::WARN.yellow(),
+            entries[duplicate.first].key, entries[duplicate.second].key, duplicate.reason);
+    }
+}
+
+/// Merges every group of duplicates into its most complete entry, filling the fields
+/// it lacks from the others. The merged entry takes the place of the first one of its group.
+pub fn merge(entries: Vec<RawEntry>, duplicates: &[Duplicate]) -> Vec<RawEntry> {
+    let mut group: Vec<usize> = (0..entries.len()).collect();
+    for duplicate in duplicates {
+        let root = find(&group, duplicate.first);
+        let other = find(&group, duplicate.second);
+        group[other.max(root)] = other.min(root);
+    }
+
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..entries.len() {
+        members.entry(find(&group, i)).or_default().push(i);
+    }
+
+    let mut slots: Vec<Option<RawEntry>> = entries.into_iter().map(Some).collect();
+    let mut merged = Vec::new();
+    for i in 0..slots.len() {
+        let Some(indices) = members.get(&i) else { continue };
+        let best = *indices.iter().max_by_key(|&&j| (completeness(slots[j].as_ref().unwrap()), std::cmp::Reverse(j))).unwrap();
+        let mut base = slots[best].take().unwrap();
+        for &j in indices.iter().filter(|&&j| j != best) {
+            let other = slots[j].take().unwrap();
+            eprintln!("{} Merged {} into {}", constants::INFO.blue(), other.key, base.key);
+            for source in &other.sources {
+                if !base.sources.contains(source) {
+                    base.sources.push(source.to_string());
+                }
+            }
+            for (name, value) in &other.fields {
+                match base.fields.iter_mut().find(|(n, _)| n == name) {
+                    Some(field) if field.1.is_empty() => field.1 = value.to_string(),
+                    Some(_) => continue,
+                    None => base.fields.push((name.to_string(), value.to_string())),
+                }
+                // The value taken from the other entry keeps the way it was written there.
+                base.raw_values.retain(|(n, _)| n != name);
+                base.raw_values.extend(other.raw_value(name).map(|raw| (name.to_string(), raw.to_string())));
+                base.macros.retain(|n| n != name);
+                if other.macros.contains(name) {
+                    base.macros.push(name.to_string());
+                }
+            }
+        }
+        merged.push(base);
+    }
+
+    merged
+}
+
+fn find(group: &[usize], mut i: usize) -> usize {
+    while group[i] != i {
+        i = group[i];
+    }
+    i
+}
+
+fn completeness(entry: &RawEntry) -> usize {
+    entry.fields.iter().filter(|(_, value)| !value.trim().is_empty()).count()
+}
+
+pub fn normalize_doi(doi: &str) -> String {
+    let doi = doi.trim().to_lowercase();
+    let doi = doi.trim_start_matches("https://").trim_start_matches("http://");
+    let doi = doi.trim_start_matches("dx.doi.org/").trim_start_matches("doi.org/").trim_start_matches("doi:");
+    doi.trim().to_string()
+}
+
+/// Digits of an ISBN, with ISBN-10 converted to ISBN-13 so both forms compare equal.
+pub fn normalize_isbn(isbn: &str) -> String {
+    let digits: String = isbn.chars().filter(|c| c.is_ascii_digit() || *c == 'X' || *c == 'x').collect();
+    if digits.len() != 10 {
+        return digits;
+    }
+    let body = format!("978{}", &digits[..9]);
+    let sum: u32 = body.chars().enumerate().map(|(i, c)| c.to_digit(10).unwrap_or(0) * if i % 2 == 0 { 1 } else { 3 }).sum();
+    format!("{}{}", body, (10 - sum % 10) % 10)
+}
+
+pub fn normalize_title(title: &str) -> String {
+    title.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+fn first_author(entry: &RawEntry) -> String {
+    let last = names::last_names(entry.get("author").unwrap_or("")).into_iter().next().unwrap_or_default();
+    last.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bibfile;
+
+    #[test]
+    fn isbn_10_to_13() {
+        assert_eq!(normalize_isbn("0-306-40615-2"), "9780306406157");
+        assert_eq!(normalize_isbn("978-0-306-40615-7"), "9780306406157");
+        assert_eq!(normalize_isbn("0-8044-2957-X"), "9780804429573");
+        assert_eq!(normalize_isbn("0-8044-2957-x"), "9780804429573");
+    }
+
+    #[test]
+    fn merge_group_of_three() {
+        let entries = bibfile::parse_entries(concat!(
+            "@article{a,\n  title = {Graphs},\n  doi = {10.1000/xyz}\n}\n",
+            "@article{b,\n  title = {Graphs},\n  doi = {https://doi.org/10.1000/XYZ},\n  year = {2020},\n  pages = {1-2}\n}\n",
+            "@article{c,\n  title = {Graphs},\n  doi = {doi:10.1000/xyz},\n  volume = {{3}}\n}\n",
+            "@article{d,\n  title = {Trees},\n  doi = {10.1000/other}\n}\n",
+        ));
+
+        let duplicates = find_duplicates(&entries);
+        assert_eq!(duplicates.iter().map(|d| (d.first, d.second)).collect::<Vec<_>>(), [(0, 1), (0, 2)]);
+
+        let merged = merge(entries, &duplicates);
+        let keys: Vec<&str> = merged.iter().map(|entry| entry.key.as_str()).collect();
+        assert_eq!(keys, ["b", "d"]);
+        assert_eq!(merged[0].get("year"), Some("2020"));
+        assert_eq!(merged[0].get("pages"), Some("1-2"));
+        assert_eq!(merged[0].get("volume"), Some("3"));
+        assert_eq!(merged[0].raw_value("volume"), Some("{{3}}"));
+    }
+}
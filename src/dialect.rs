@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use colored::Colorize;
+
+use crate::constants;
+use crate::month::Month;
+use crate::printer::{Field, Value};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Dialect {
+    BibTeX,
+    BibLaTeX
+}
+
+impl Dialect {
+    pub fn parse(name: &str) -> Result<Dialect, String> {
+        match name.to_lowercase().as_str() {
+            "bibtex" => Ok(Dialect::BibTeX),
+            "biblatex" => Ok(Dialect::BibLaTeX),
+            _ => Err(format!("Unknown dialect: {} (expected bibtex or biblatex)", name)),
+        }
+    }
+}
+
+/// Biblatex names that are read into the BibTeX fields of the entry structs.
+pub const BIBLATEX_ALIASES: [&str; 3] = ["journaltitle", "location", "date"];
+
+/// Fields that only biblatex understands and that have no BibTeX counterpart.
+const BIBLATEX_ONLY: [&str; 8] = ["urldate", "eprint", "eprinttype", "subtitle", "titleaddon", "origdate", "eventdate", "venue"];
+
+/// Rewrites biblatex names (journaltitle, location, date, @online) into the
+/// BibTeX names the entry structs validate, and returns the entry type to use.
+pub fn normalize_input(entry_type: &str, key: &str, fields: &mut HashMap<String, String>, target: Dialect) -> String {
+    rename_field(fields, "journaltitle", "journal");
+    rename_field(fields, "location", "address");
+
+    if let Some(date) = fields.remove("date") {
+        let (year, month, lossy) = split_date(&date);
+        fields.entry("year".to_string()).or_insert(year);
+        if let Some(month) = month {
+            fields.entry("month".to_string()).or_insert(month);
+        }
+        if lossy && target == Dialect::BibTeX {
+            eprintln!("{} Lossy conversion in entry {}: date {} reduced to year and month", constants::WARN.yellow(), key, date);
+        }
+    }
+
+    if target == Dialect::BibTeX {
+        for name in BIBLATEX_ONLY {
+            if fields.contains_key(name) {
+                eprintln!("{} Lossy conversion in entry {}: field {} has no BibTeX equivalent", constants::WARN.yellow(), key, name);
+            }
+        }
+    }
+
+    match entry_type {
+        "online" => "misc".to_string(),
+        _ => entry_type.to_string(),
+    }
+}
+
+/// Maps the canonical BibTeX fields of an entry to the names used by `dialect`.
+pub fn map_output(entry_type: &str, fields: Vec<Field>, dialect: Dialect) -> (String, Vec<Field>) {
+    match dialect {
+        Dialect::BibTeX => {
+            let has_howpublished = fields.iter().any(|(n, v)| n == "howpublished" && !v.as_str().is_empty());
+            let mut mapped = Vec::new();
+            for (name, value) in fields {
+                match name.as_str() {
+                    "url" if !has_howpublished && !value.as_str().is_empty() => {
+                        mapped.push(("howpublished".to_string(), Value::Text(format!("\\url{{{}}}", value.as_str()))));
+                    }
+                    _ => mapped.push((name, value)),
+                }
+            }
+            (entry_type.to_string(), mapped)
+        }
+        Dialect::BibLaTeX => {
+            let month = fields.iter().find(|(n, _)| n == "month").map(|(_, v)| v.as_str().to_string()).unwrap_or_default();
+            let has_url = fields.iter().any(|(n, v)| n == "url" && !v.as_str().is_empty());
+            let mut mapped = Vec::new();
+            for (name, value) in fields {
+                match name.as_str() {
+                    "journal" => mapped.push(("journaltitle".to_string(), value)),
+                    "address" => mapped.push(("location".to_string(), value)),
+                    "year" => mapped.push(("date".to_string(), Value::Text(join_date(value.as_str(), &month)))),
+                    "month" => {}
+                    _ => mapped.push((name, value)),
+                }
+            }
+            let entry_type = if entry_type == "misc" && has_url { "online" } else { entry_type };
+            (entry_type.to_string(), mapped)
+        }
+    }
+}
+
+/// Maps the fields of an entry the structs do not model, written in either dialect, to the
+/// names used by `dialect`.
+pub fn map_raw(entry_type: &str, fields: Vec<Field>, dialect: Dialect) -> (String, Vec<Field>) {
+    let names: Vec<String> = fields.iter().map(|(name, _)| name.to_string()).collect();
+    let mut canonical = Vec::new();
+    for (name, value) in fields {
+        match name.as_str() {
+            "journaltitle" | "location" | "date" if bibtex_names(&name).iter().any(|n| names.iter().any(|name| name == n)) => {}
+            "journaltitle" => canonical.push(("journal".to_string(), value)),
+            "location" => canonical.push(("address".to_string(), value)),
+            "date" => {
+                let (year, month, _) = split_date(value.as_str());
+                canonical.push(("year".to_string(), Value::Text(year)));
+                if let Some(month) = month {
+                    canonical.push(("month".to_string(), Value::Macro(month)));
+                }
+            }
+            _ => canonical.push((name, value)),
+        }
+    }
+    let entry_type = if entry_type == "online" { "misc" } else { entry_type };
+    map_output(entry_type, canonical, dialect)
+}
+
+/// Whether `name` and `other` hold the same data, possibly under the names of different
+/// dialects (journal and journaltitle, address and location, year or month and date).
+pub fn same_field(name: &str, other: &str) -> bool {
+    let other = bibtex_names(other);
+    bibtex_names(name).iter().any(|name| other.contains(name))
+}
+
+fn bibtex_names(name: &str) -> Vec<&str> {
+    match name {
+        "journaltitle" => vec!["journal"],
+        "location" => vec!["address"],
+        "date" => vec!["year", "month"],
+        _ => vec![name],
+    }
+}
+
+fn rename_field(fields: &mut HashMap<String, String>, from: &str, to: &str) {
+    if let Some(value) = fields.remove(from) {
+        fields.entry(to.to_string()).or_insert(value);
+    }
+}
+
+/// Splits an ISO 8601 date into year and month macro. A range of months within one year is
+/// kept as a month range; day precision and other ranges are flagged as lossy.
+fn split_date(date: &str) -> (String, Option<String>, bool) {
+    let mut ends = date.split('/');
+    let start: Vec<&str> = ends.next().unwrap_or("").split('-').collect();
+    let end: Option<Vec<&str>> = ends.next().map(|end| end.split('-').collect());
+    let year = start[0].to_string();
+    let first = start.get(1).and_then(|m| Month::parse(m).ok());
+    let last = end.as_ref()
+        .filter(|end| end.len() == 2 && end[0] == start[0])
+        .and_then(|end| Month::parse(end[1]).ok());
+
+    let month = match (first, last) {
+        (Some(Month::Single(first)), Some(Month::Single(last))) => Some(Month::Range(first, last)),
+        (first, _) => first,
+    };
+    let lossy = start.len() > 2 || (end.is_some() && last.is_none());
+    (year, month.map(|month| month.to_bibtex()), lossy)
+}
+
+fn join_date(year: &str, month: &str) -> String {
+    match Month::parse(month) {
+        Ok(month) => month.to_date(year),
+        Err(_) => year.to_string(),
+    }
+}
@@ -0,0 +1,95 @@
+use std::{collections::HashMap, fs, io};
+use colored::Colorize;
+
+use crate::bibfile::{self, RawEntry};
+use crate::dedup;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum MatchBy {
+    Key,
+    Doi
+}
+
+impl MatchBy {
+    pub fn parse(name: &str) -> Result<MatchBy, String> {
+        match name {
+            "key" => Ok(MatchBy::Key),
+            "doi" => Ok(MatchBy::Doi),
+            _ => Err(format!("Unknown match criterion: {} (expected key or doi)", name)),
+        }
+    }
+}
+
+/// Compares two bibliography files entry by entry, reporting added and removed entries
+/// and per-field changes. Whitespace and brace style are not considered changes.
+pub fn run(old_file: &str, new_file: &str, match_by: MatchBy) -> io::Result<()> {
+    let old_entries = bibfile::parse_entries(&fs::read_to_string(old_file)?);
+    let new_entries = bibfile::parse_entries(&fs::read_to_string(new_file)?);
+
+    let new_index: HashMap<String, &RawEntry> = new_entries.iter().map(|e| (identity(e, match_by), e)).collect();
+    let old_index: HashMap<String, &RawEntry> = old_entries.iter().map(|e| (identity(e, match_by), e)).collect();
+
+    for old in &old_entries {
+        match new_index.get(&identity(old, match_by)) {
+            Some(new) => print_changes(old, new),
+            None => println!("{}", format!("- {} (removed)", old.key).red()),
+        }
+    }
+    for new in &new_entries {
+        if !old_index.contains_key(&identity(new, match_by)) {
+            println!("{}", format!("+ {} (added)", new.key).green());
+        }
+    }
+
+    Ok(())
+}
+
+fn identity(entry: &RawEntry, match_by: MatchBy) -> String {
+    let doi = entry.get("doi").map(dedup::normalize_doi).unwrap_or_default();
+    if match_by == MatchBy::Doi && !doi.is_empty() {
+        doi
+    } else {
+        entry.key.to_string()
+    }
+}
+
+/// Field value without braces, quotes and repeated whitespace.
+pub fn normalize_value(value: &str) -> String {
+    value.chars()
+        .filter(|c| !matches!(c, '{' | '}' | '"'))
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+fn print_changes(old: &RawEntry, new: &RawEntry) {
+    let mut changes = Vec::new();
+
+    if old.key != new.key {
+        changes.push(format!("    key: {} -> {}", old.key, new.key));
+    }
+    if old.entry_type != new.entry_type {
+        changes.push(format!("    type: {} -> {}", old.entry_type, new.entry_type));
+    }
+    // Values are compared normalised but shown as written.
+    for (name, old_value) in &old.fields {
+        match new.get(name) {
+            Some(new_value) if normalize_value(new_value) == normalize_value(old_value) => {}
+            Some(new_value) => changes.push(format!("    {}: \"{}\" -> \"{}\"", name, old_value.trim(), new_value.trim())),
+            None => changes.push(format!("    - {}: \"{}\"", name, old_value.trim()).red().to_string()),
+        }
+    }
+    for (name, new_value) in &new.fields {
+        if old.get(name).is_none() {
+            changes.push(format!("    + {}: \"{}\"", name, new_value.trim()).green().to_string());
+        }
+    }
+
+    if !changes.is_empty() {
+        println!("{}", format!("~ {}", new.key).yellow());
+        for change in changes {
+            println!("{}", change);
+        }
+    }
+}
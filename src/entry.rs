@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+use crate::article::Article;
+use crate::book::Book;
+use crate::collection::Collection;
+use crate::crossref::Resolved;
+use crate::dialect::{self, Dialect};
+use crate::keygen::KeyPattern;
+use crate::misc::Misc;
+use crate::printer::{Field, Value};
+use crate::proceedings::Proceedings;
+
+pub const SUPPORTED_TYPES: [&str; 5] = ["article", "book", "incollection", "inproceedings", "misc"];
+
+pub enum Entry {
+    Article(Article),
+    Book(Book),
+    Collection(Collection),
+    Proceedings(Proceedings),
+    Misc(Misc)
+}
+
+impl Entry {
+    pub fn new(entry_type: &str, fields: &HashMap<String, String>) -> Result<Entry, String> {
+        match entry_type {
+            "article" => Article::new(fields).map(Entry::Article),
+            "book" => Book::new(fields).map(Entry::Book),
+            "incollection" => Collection::new(fields).map(Entry::Collection),
+            "inproceedings" => Proceedings::new(fields).map(Entry::Proceedings),
+            "misc" => Misc::new(fields).map(Entry::Misc),
+            _ => Err(format!("Unknown entry type: {}", entry_type)),
+        }
+    }
+
+    pub fn entry_type(&self) -> &'static str {
+        match self {
+            Entry::Article(_) => "article",
+            Entry::Book(_) => "book",
+            Entry::Collection(_) => "incollection",
+            Entry::Proceedings(_) => "inproceedings",
+            Entry::Misc(_) => "misc",
+        }
+    }
+
+    pub fn fields(&self) -> Vec<Field> {
+        match self {
+            Entry::Article(article) => article.fields(),
+            Entry::Book(book) => book.fields(),
+            Entry::Collection(collection) => collection.fields(),
+            Entry::Proceedings(proceeding) => proceeding.fields(),
+            Entry::Misc(misc) => misc.fields(),
+        }
+    }
+
+    pub fn generate_key(&self, pattern: &KeyPattern) -> String {
+        pattern.generate(&self.fields())
+    }
+
+    /// Fields of the entry named for `dialect`. When `crossref` is given, inherited fields
+    /// are left to the parent and the crossref/xdata links are written instead.
+    pub fn output(&self, dialect: Dialect, crossref: Option<&Resolved>) -> (String, Vec<Field>) {
+        let mut fields = self.fields();
+        if let Some(resolved) = crossref {
+            // biblatex writes year and month as one date, so a month of the child's own keeps the inherited year.
+            let own_month = fields.iter().any(|(name, _)| name == "month") && !resolved.is_inherited("month", dialect);
+            let keep_year = dialect == Dialect::BibLaTeX && own_month;
+            fields.retain(|(name, _)| !resolved.is_inherited(name, dialect) || (name == "year" && keep_year));
+            fields.extend(resolved.links.iter().map(|(name, value)| (name.to_string(), Value::Text(value.to_string()))));
+        }
+        dialect::map_output(self.entry_type(), fields, dialect)
+    }
+}
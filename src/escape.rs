@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use colored::Colorize;
+
+use crate::constants;
+use crate::printer;
+use crate::tex;
+
+/// Characters LaTeX reads as commands when they are not preceded by a backslash,
+/// with the name of the rule reporting them.
+const RULES: [(char, &str); 4] = [
+    ('&', "unescaped-ampersand"),
+    ('%', "unescaped-percent"),
+    ('#', "unescaped-hash"),
+    ('_', "unescaped-underscore")
+];
+
+/// A `$` opening math mode that is never closed, most likely meant as a dollar sign.
+const UNBALANCED_MATH: &str = "unbalanced-math";
+
+pub struct Issue {
+    pub rule: &'static str,
+    pub character: char,
+    pub offset: usize
+}
+
+/// Special characters of `value` that are neither escaped nor in math mode (`$...$`, `\(...\)`).
+/// The arguments of `\url` and `\href` are not checked.
+pub fn check(value: &str) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let mut math: Option<usize> = None;
+    let mut i = 0;
+
+    while let Some(c) = value[i..].chars().next() {
+        if c == '\\' {
+            let rest = &value[i + 1..];
+            if rest.starts_with("url{") || rest.starts_with("href{") {
+                let open = i + 1 + rest.find('{').unwrap();
+                i = tex::group_end(&value[open..]).map(|end| open + end + 1).unwrap_or(value.len());
+                continue;
+            }
+            match rest.chars().next() {
+                Some('(') => math = Some(i),
+                Some(')') => math = None,
+                _ => {}
+            }
+            i += 1 + rest.chars().next().map(char::len_utf8).unwrap_or(0);
+            continue;
+        }
+        if c == '$' {
+            math = if math.is_some() { None } else { Some(i) };
+        } else if math.is_none() {
+            if let Some((_, rule)) = RULES.iter().find(|(special, _)| *special == c) {
+                issues.push(Issue { rule, character: c, offset: i });
+            }
+        }
+        i += c.len_utf8();
+    }
+    if let Some(offset) = math.filter(|&offset| value[offset..].starts_with('$')) {
+        // Read the rest again as text, taking the lone `$` as a dollar sign.
+        let mut escaped = value.to_string();
+        escaped.insert(offset, '\\');
+        issues = check(&escaped).into_iter().map(|mut issue| {
+            if issue.offset > offset {
+                issue.offset -= 1;
+            }
+            issue
+        }).collect();
+        issues.push(Issue { rule: UNBALANCED_MATH, character: '$', offset });
+    }
+
+    issues.sort_by_key(|issue| issue.offset);
+    issues
+}
+
+/// `value` with a backslash in front of every character reported by `check`.
+pub fn fix(value: &str) -> String {
+    let mut fixed = value.to_string();
+    for issue in check(value).iter().rev() {
+        fixed.insert(issue.offset, '\\');
+    }
+    fixed
+}
+
+/// Reports the unescaped special characters of every field of an entry, escaping them when `apply_fix` is set.
+/// Fields written without delimiters (`macros`) are skipped, `#` concatenating macros and strings there,
+/// as in `jun # "/" # jul`.
+pub fn review(key: &str, fields: &mut HashMap<String, String>, macros: &[String], apply_fix: bool) {
+    let mut names: Vec<String> = fields.keys()
+        .filter(|name| !printer::VERBATIM_FIELDS.contains(&name.as_str()) && !macros.contains(name))
+        .cloned()
+        .collect();
+    names.sort();
+
+    for name in names {
+        let value = &fields[&name];
+        if value.starts_with("\\url{") {
+            continue;
+        }
+        let issues = check(value);
+        for issue in &issues {
+            match apply_fix {
+                true => eprintln!("{} Escaped {} in {} of {} ({})", constants::INFO.blue(), issue.character, name, key, issue.rule),
+                false => eprintln!("{} Unescaped {} in {} of {} ({})", constants::WARN.yellow(), issue.character, name, key, issue.rule),
+            }
+        }
+        if apply_fix && !issues.is_empty() {
+            let fixed = fix(value);
+            fields.insert(name, fixed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(value: &str) -> Vec<&'static str> {
+        check(value).iter().map(|issue| issue.rule).collect()
+    }
+
+    #[test]
+    fn special_characters() {
+        assert_eq!(rules("Smith & Sons, 100% of #1 my_file"), ["unescaped-ampersand", "unescaped-percent", "unescaped-hash", "unescaped-underscore"]);
+        assert!(rules("Smith \\& Sons, 100\\% of \\#1 my\\_file").is_empty());
+        assert_eq!(fix("Smith & Sons_2"), "Smith \\& Sons\\_2");
+    }
+
+    #[test]
+    fn math_mode() {
+        assert!(rules("the $x_1 + y_2$ case").is_empty());
+        assert!(rules("the \\(x_1\\) case").is_empty());
+        assert_eq!(rules("$x_1$ and y_2"), ["unescaped-underscore"]);
+    }
+
+    #[test]
+    fn urls() {
+        assert!(rules("see \\url{https://a.org/my_page#top?a=1&b=2}").is_empty());
+        assert_eq!(rules("\\href{https://a.org/x_y}{the_page}"), ["unescaped-underscore"]);
+    }
+
+    #[test]
+    fn lone_dollar() {
+        assert_eq!(rules("costs $5 & more"), ["unbalanced-math", "unescaped-ampersand"]);
+        assert_eq!(fix("costs $5 & more"), "costs \\$5 \\& more");
+        assert!(rules("costs \\$5").is_empty());
+    }
+
+    #[test]
+    fn macros_are_skipped() {
+        let mut fields: HashMap<String, String> = [
+            ("journal".to_string(), "jcomp # \" Letters\"".to_string()),
+            ("note".to_string(), "Part #2".to_string())
+        ].into_iter().collect();
+        review("a", &mut fields, &["journal".to_string()], true);
+        assert_eq!(fields["journal"], "jcomp # \" Letters\"");
+        assert_eq!(fields["note"], "Part \\#2");
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::{BTreeMap, HashSet};
+use colored::Colorize;
+
+use crate::constants;
+use crate::names;
+use crate::printer::Field;
+use crate::tex;
+
+pub const DEFAULT_PATTERN: &str = "[auth:lower][year]";
+
+/// Words skipped by `shorttitle` and `veryshorttitle`.
+const FUNCTION_WORDS: [&str; 16] = ["a", "an", "the", "of", "on", "in", "and", "or", "for", "to", "with", "at", "by", "from", "as", "into"];
+
+/// Characters BibTeX does not accept in citation keys.
+const INVALID_KEY_CHARS: &str = " \t\n,{}()\"#%'=~\\";
+
+/// Which entries get a key generated from the pattern instead of keeping their own.
+#[derive(Clone, Copy, PartialEq)]
+pub enum KeyPolicy {
+    Keep,
+    Regenerate,
+    MissingOrInvalid
+}
+
+impl KeyPolicy {
+    pub fn parse(name: &str) -> Result<KeyPolicy, String> {
+        match name {
+            "keep" => Ok(KeyPolicy::Keep),
+            "regenerate" => Ok(KeyPolicy::Regenerate),
+            "missing-or-invalid" => Ok(KeyPolicy::MissingOrInvalid),
+            _ => Err(format!("Unknown key policy: {} (expected keep, regenerate or missing-or-invalid)", name)),
+        }
+    }
+
+    pub fn should_regenerate(&self, key: &str) -> bool {
+        match self {
+            KeyPolicy::Keep => false,
+            KeyPolicy::Regenerate => true,
+            KeyPolicy::MissingOrInvalid => !is_valid_key(key),
+        }
+    }
+}
+
+pub fn is_valid_key(key: &str) -> bool {
+    !key.is_empty() && !key.chars().any(|c| INVALID_KEY_CHARS.contains(c))
+}
+
+enum Part {
+    Literal(String),
+    Field { name: String, filters: Vec<String> }
+}
+
+/// A citation key pattern such as `[auth:lower][year][shorttitle:1]`.
+///
+/// Supported fields are `auth`, `auth.etal`, `authors`, `year`, `shortyear`, `title`,
+/// `shorttitle`, `veryshorttitle` and any other field name of the entry. Filters are
+/// `lower`, `upper` and a number `N` keeping only the first N words.
+pub struct KeyPattern {
+    parts: Vec<Part>
+}
+
+impl KeyPattern {
+    pub fn parse(pattern: &str) -> Result<KeyPattern, String> {
+        let mut parts = Vec::new();
+        let mut rest = pattern;
+
+        while !rest.is_empty() {
+            match rest.find('[') {
+                Some(0) => {
+                    let end = rest.find(']').ok_or(format!("Unclosed [ in key pattern: {}", pattern))?;
+                    let mut tokens = rest[1..end].split(':');
+                    let name = tokens.next().unwrap_or("").trim().to_lowercase();
+                    if name.is_empty() {
+                        return Err(format!("Empty field in key pattern: {}", pattern));
+                    }
+                    let filters: Vec<String> = tokens.map(|f| f.trim().to_lowercase()).collect();
+                    for filter in &filters {
+                        if filter != "lower" && filter != "upper" && filter.parse::<usize>().is_err() {
+                            return Err(format!("Unknown key pattern filter: {}", filter));
+                        }
+                    }
+                    parts.push(Part::Field { name, filters });
+                    rest = &rest[end + 1..];
+                }
+                Some(start) => {
+                    parts.push(Part::Literal(rest[..start].to_string()));
+                    rest = &rest[start..];
+                }
+                None => {
+                    parts.push(Part::Literal(rest.to_string()));
+                    rest = "";
+                }
+            }
+        }
+
+        Ok(KeyPattern { parts })
+    }
+
+    pub fn generate(&self, fields: &[Field]) -> String {
+        let mut key = String::new();
+
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => key.push_str(text),
+                Part::Field { name, filters } => {
+                    let mut words = expand(name, fields);
+                    for filter in filters {
+                        match filter.as_str() {
+                            "lower" => words = words.iter().map(|w| w.to_lowercase()).collect(),
+                            "upper" => words = words.iter().map(|w| w.to_uppercase()).collect(),
+                            n => words.truncate(n.parse().unwrap_or(words.len())),
+                        }
+                    }
+                    for word in words {
+                        key.push_str(&word);
+                    }
+                }
+            }
+        }
+
+        tex::fold(&key).chars().filter(|c| c.is_ascii_alphanumeric() || "-_:.".contains(*c)).collect()
+    }
+}
+
+impl Default for KeyPattern {
+    fn default() -> KeyPattern {
+        KeyPattern::parse(DEFAULT_PATTERN).unwrap()
+    }
+}
+
+/// Appends a, b, c, ... to keys shared by several entries so that every key is unique.
+/// Suffixes follow the alphabetical order of the titles, then the input order.
+pub fn disambiguate(keys: &mut [String], titles: &[String]) {
+    let mut groups: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        groups.entry(key.to_string()).or_default().push(i);
+    }
+    let mut taken: HashSet<String> = keys.iter().cloned().collect();
+
+    for (key, mut members) in groups {
+        if members.len() < 2 {
+            continue;
+        }
+        eprintln!("{} Key {} is shared by {} entries, adding suffixes", constants::WARN.yellow(), key, members.len());
+        members.sort_by_key(|&i| (titles[i].to_lowercase(), i));
+
+        let mut n = 0;
+        for i in members {
+            loop {
+                let candidate = format!("{}{}", key, suffix(n));
+                n += 1;
+                if taken.insert(candidate.clone()) {
+                    keys[i] = candidate;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Bijective base-26 suffix: a, b, ..., z, aa, ab, ...
+fn suffix(mut n: usize) -> String {
+    let mut letters = Vec::new();
+    loop {
+        letters.push((b'a' + (n % 26) as u8) as char);
+        if n < 26 {
+            break;
+        }
+        n = n / 26 - 1;
+    }
+    letters.iter().rev().collect()
+}
+
+fn field<'a>(fields: &'a [Field], name: &str) -> &'a str {
+    fields.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str()).unwrap_or("")
+}
+
+/// Last names of the authors, or of the editors when the entry has no author.
+fn last_names(fields: &[Field]) -> Vec<String> {
+    let mut names = field(fields, "author");
+    if names.is_empty() {
+        names = field(fields, "editor");
+    }
+    names::last_names(names)
+}
+
+fn title_words(fields: &[Field], skip_function_words: bool) -> Vec<String> {
+    tex::to_plain(field(fields, "title"))
+        .split_whitespace()
+        .map(|word| word.chars().filter(|c| c.is_alphanumeric()).collect::<String>())
+        .filter(|word| !word.is_empty())
+        .filter(|word| !skip_function_words || !FUNCTION_WORDS.contains(&word.to_lowercase().as_str()))
+        .map(|word| capitalize(&word))
+        .collect()
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn expand(name: &str, fields: &[Field]) -> Vec<String> {
+    match name {
+        "auth" => last_names(fields).into_iter().take(1).collect(),
+        "auth.etal" => {
+            let names = last_names(fields);
+            if names.len() > 2 {
+                vec![names[0].to_string(), "EtAl".to_string()]
+            } else {
+                names
+            }
+        }
+        "authors" => last_names(fields),
+        "year" => vec![field(fields, "year").to_string()],
+        "shortyear" => {
+            let year = field(fields, "year");
+            vec![year[year.len().saturating_sub(2)..].to_string()]
+        }
+        "title" => title_words(fields, false),
+        "shorttitle" => title_words(fields, true).into_iter().take(3).collect(),
+        "veryshorttitle" => title_words(fields, true).into_iter().take(1).collect(),
+        other => field(fields, other).split_whitespace().map(str::to_string).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn suffixes_follow_title_order() {
+        let mut keys = strings(&["smith2020", "jones2019", "smith2020"]);
+        disambiguate(&mut keys, &strings(&["Zebras", "Other", "Apples"]));
+        assert_eq!(keys, ["smith2020b", "jones2019", "smith2020a"]);
+
+        // Same titles fall back to the input order.
+        let mut keys = strings(&["smith2020", "smith2020"]);
+        disambiguate(&mut keys, &strings(&["Same", "Same"]));
+        assert_eq!(keys, ["smith2020a", "smith2020b"]);
+    }
+
+    #[test]
+    fn suffix_skips_existing_keys() {
+        let mut keys = strings(&["smith2020", "smith2020a", "smith2020"]);
+        disambiguate(&mut keys, &strings(&["A", "B", "C"]));
+        assert_eq!(keys, ["smith2020b", "smith2020a", "smith2020c"]);
+    }
+
+    #[test]
+    fn suffix_rollover() {
+        assert_eq!(suffix(0), "a");
+        assert_eq!(suffix(25), "z");
+        assert_eq!(suffix(26), "aa");
+        assert_eq!(suffix(27), "ab");
+        assert_eq!(suffix(26 + 26 * 26), "aaa");
+
+        let mut keys = vec!["k".to_string(); 28];
+        let titles: Vec<String> = (0..28).map(|i| format!("{:02}", i)).collect();
+        disambiguate(&mut keys, &titles);
+        assert_eq!(keys[25], "kz");
+        assert_eq!(keys[26], "kaa");
+        assert_eq!(keys[27], "kab");
+    }
+}
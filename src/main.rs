@@ -8,115 +8,123 @@ mod article;
 mod constants;
 mod book;
 mod collection;
+mod misc;
+mod entry;
+mod bibfile;
+mod printer;
+mod dialect;
+mod options;
+mod crossref;
+mod keygen;
+mod bibliography;
+mod citations;
+mod rekey;
+mod prune;
+mod report;
+mod similarity;
+mod dedup;
+mod merge;
+mod split;
+mod diff;
+mod merge_driver;
+mod sort;
+mod names;
+mod tex;
+mod escape;
+mod protect;
+mod case;
+mod month;
 
-fn main() -> std::io::Result<()> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        eprintln!("Usage: {} <file>", args[0]);
-        std::process::exit(1);
-    }
+use options::Options;
 
-    let file_name = &args[1];
-    
-    let mut file = File::open(file_name)?;
-    let mut buffer = String::new();
-    file.read_to_string(&mut buffer)?;
-    
-    let _ = extract_entries(&buffer);
+const USAGE: &str = "Usage: bibliohelp [options] <file>
+       bibliohelp convert --to bibtex|biblatex [options] <file>
+       bibliohelp rekey [--dry-run] [options] <file> <tex files...>
+       bibliohelp prune [options] <file> <aux|bcf|tex files...>
+       bibliohelp report [options] <file> <aux|bcf|tex files...>
+       bibliohelp split [--by type|year|tag] [-o <directory>] [options] <file>
+       bibliohelp merge [--on-conflict suffix|keep-first|keep-last|regenerate] [options] <files...>
+       bibliohelp diff [--match key|doi] <old file> <new file>
+       bibliohelp merge-driver <base> <ours> <theirs>
+Options: [-o <output>] [--inline-crossref] [--keys keep|regenerate|missing-or-invalid] [--key-pattern <pattern>] [--dedup report|merge]
+         [--sort key|year|author|type[:asc|desc],...] [--reverse]
+         [--field-order <fields>] [--align <width>|longest|none] [--indent <width>|tab]
+         [--trailing-comma yes|no] [--delimiters braces|quotes] [--encoding ascii|utf8] [--lossless]
+         [--invalid drop|keep|comment] [--name-style last-first|first-last] [--initials keep|abbreviate|expand]
+         [--fix-escapes] [--protect all|smart|none] [--protect-words <file>]
+         [--title-case title|sentence] [--small-words <words>] [--hyphenated all|first] [--after-colon capitalize|keep]";
 
-    Ok(())
-}
-
-fn extract_entries(input: &str) -> std::io::Result<()> {
-    let mut iter = input.chars().peekable();
-    let mut entry_content = String::new();
-    let mut brace_count;
-    let mut entry_type = String::new();
-
-    let mut output_file = File::create("biblio.bib")?;
+fn main() -> std::io::Result<()> {
+    let args: Vec<String> = env::args().collect();
+    let (command, rest) = match args.get(1).map(String::as_str) {
+        Some(command @ ("convert" | "rekey" | "prune" | "report" | "merge" | "split" | "diff" | "merge-driver")) => (command, &args[2..]),
+        _ => ("format", &args[1..]),
+    };
 
-    while let Some(c) = iter.next() {
-        if c == '@' {
-            entry_type.clear();
-            entry_content.clear();
-            brace_count = 0;
+    let mut options = match Options::load(rest) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{} {}", constants::ERR.red(), e);
+            eprintln!("{}", USAGE);
+            std::process::exit(1);
+        }
+    };
 
-            while let Some(&next_char) = iter.peek() {
-                if next_char == '{' {
-                    iter.next();
-                    brace_count += 1;
-                    break;
-                }
-                entry_type.push(next_char);
-                iter.next();
-            }
+    let valid = match command {
+        "convert" => options.inputs.len() == 1 && options.dialect.is_some(),
+        "rekey" | "prune" | "report" | "merge" => options.inputs.len() >= 2,
+        "diff" => options.inputs.len() == 2,
+        "merge-driver" => options.inputs.len() == 3,
+        _ => options.inputs.len() == 1,
+    };
+    if !valid {
+        eprintln!("{}", USAGE);
+        std::process::exit(1);
+    }
 
-            while let Some(&next_char) = iter.peek() {
-                if next_char == '{' {
-                    brace_count += 1;
-                } else if next_char == '}' {
-                    brace_count -= 1;
-                    if brace_count == 0 {
-                        iter.next();
-                        break;
-                    }
-                }
-                entry_content.push(next_char);
-                iter.next();
+    match command {
+        "merge" => return merge::run(&mut options),
+        "diff" => return diff::run(&options.inputs[0], &options.inputs[1], options.match_by),
+        "merge-driver" => {
+            let conflicts = merge_driver::run(&options.inputs[0], &options.inputs[1], &options.inputs[2], &options.format)?;
+            if conflicts > 0 {
+                eprintln!("{} {} conflicting field(s) left in {}", constants::ERR.red(), conflicts, options.inputs[1]);
+                std::process::exit(1);
             }
+            return Ok(());
+        }
+        _ => {}
+    }
 
-            let entry_id: String = entry_content.chars().take_while(|&c| c != ',').collect();
+    let file_name = &options.inputs[0];
 
-            match entry_type.trim() {
-                "inproceedings" => {
-                    match proceedings::Proceedings::new(&entry_content) {
-                        Ok(proceeding) => {
-                            proceeding.print(&mut output_file).unwrap();
-                        }
-                        Err(e) => {
-                            eprintln!("{} Error processing inproceedings entry {}: {}", constants::ERR, entry_id.red(), e);
-                        }
-                    }
-                }
+    let mut file = File::open(file_name)?;
+    let mut buffer = String::new();
+    file.read_to_string(&mut buffer)?;
 
-                "article" => {
-                    match article::Article::new(&entry_content) {
-                        Ok(article) => {
-                            article.print(&mut output_file).unwrap();
-                        }
-                        Err(e) => {
-                            eprintln!("{} Error processing article entry {}: {}", constants::ERR.red(), entry_id, e);
-                        }
-                    }
-                }
-                "book" => {
-                    match book::Book::new(&entry_content) {
-                        Ok(book) => {
-                            book.print(&mut output_file).unwrap();
-                        }
-                        Err(e) => {
-                            eprintln!("{} Error processing book entry {}: {}", constants::ERR.red(), entry_id, e);
-                        }
-                    }
-                }
-                "incollection" => {
-                    match collection::Collection::new(&entry_content) {
-                        Ok(collection) => {
-                            collection.print(&mut output_file).unwrap();
-                        }
-                        Err(e) => {
-                            eprintln!("{} Error processing collection entry {}: {}", constants::ERR.red(), entry_id, e);
-                        }
-                    }
-                }
-                "misc" => {
-                }
-                _ => {
-                    eprintln!("{} Unknown entry type: {}", constants::INFO.blue(), entry_type);
-                }
-            }
+    match command {
+        "rekey" => rekey::run(&buffer, &mut options),
+        "prune" => prune::run(&buffer, &options),
+        "report" => report::run(&buffer, &options),
+        "split" => split::run(&buffer, &mut options),
+        _ => {
+            let _ = extract_entries(&buffer, &options);
+            Ok(())
         }
     }
+}
 
-    Ok(())
-}
\ No newline at end of file
+fn extract_entries(input: &str, options: &Options) -> std::io::Result<()> {
+    let mut output_file = File::create(options.output())?;
+    if options.lossless {
+        let blocks = bibfile::parse_document(input);
+        let raws = blocks.iter().filter_map(|block| match block {
+            bibfile::Block::Entry(raw) => Some(raw.clone()),
+            bibfile::Block::Text(_) => None,
+        }).collect();
+        let records = bibliography::process_entries(raws, options);
+        return bibliography::write_lossless(&mut output_file, &blocks, &records, options);
+    }
+    let records = bibliography::process(input, options);
+    bibliography::write(&mut output_file, &records, options)
+}
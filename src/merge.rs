@@ -0,0 +1,84 @@
+use std::{collections::HashMap, fs::{self, File}, io};
+use colored::Colorize;
+
+use crate::bibfile::{self, RawEntry};
+use crate::bibliography;
+use crate::constants;
+use crate::dedup;
+use crate::keygen::KeyPattern;
+use crate::options::Options;
+use crate::printer;
+
+/// What to do when entries from different files share a key but are not duplicates.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ConflictStrategy {
+    Suffix,
+    KeepFirst,
+    KeepLast,
+    Regenerate
+}
+
+impl ConflictStrategy {
+    pub fn parse(name: &str) -> Result<ConflictStrategy, String> {
+        match name {
+            "suffix" => Ok(ConflictStrategy::Suffix),
+            "keep-first" => Ok(ConflictStrategy::KeepFirst),
+            "keep-last" => Ok(ConflictStrategy::KeepLast),
+            "regenerate" => Ok(ConflictStrategy::Regenerate),
+            _ => Err(format!("Unknown conflict strategy: {} (expected suffix, keep-first, keep-last or regenerate)", name)),
+        }
+    }
+}
+
+/// Merges every input file into one bibliography: duplicates are unified, key conflicts
+/// resolved with `--on-conflict`, and each entry is preceded by the files it came from.
+pub fn run(options: &mut Options) -> io::Result<()> {
+    let mut raws = Vec::new();
+    for file_name in &options.inputs {
+        let content = fs::read_to_string(file_name)?;
+        for mut raw in bibfile::parse_entries(&content) {
+            raw.sources.push(file_name.to_string());
+            raws.push(raw);
+        }
+    }
+
+    let duplicates = dedup::find_duplicates(&raws);
+    dedup::report(&raws, &duplicates);
+    raws = dedup::merge(raws, &duplicates);
+    raws = resolve_conflicts(raws, options.on_conflict, &options.key_pattern);
+
+    options.dedup = None;
+    let records = bibliography::process_entries(raws, options);
+    let mut output_file = File::create(options.output())?;
+    bibliography::write(&mut output_file, &records, options)
+}
+
+fn resolve_conflicts(mut raws: Vec<RawEntry>, strategy: ConflictStrategy, pattern: &KeyPattern) -> Vec<RawEntry> {
+    let mut groups: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, raw) in raws.iter().enumerate().filter(|(_, raw)| !raw.is_special()) {
+        groups.entry(raw.key.clone()).or_default().push(i);
+    }
+
+    let mut dropped = vec![false; raws.len()];
+    for (key, members) in groups.into_iter().filter(|(_, members)| members.len() > 1) {
+        let kept = match strategy {
+            ConflictStrategy::Suffix => continue,
+            ConflictStrategy::KeepFirst => members[0],
+            ConflictStrategy::KeepLast => members[members.len() - 1],
+            ConflictStrategy::Regenerate => {
+                for i in members {
+                    let new_key = pattern.generate(&printer::raw_fields(&raws[i]));
+                    eprintln!("{} Key conflict on {}: renamed entry from {} to {}", constants::INFO.blue(), key, raws[i].sources.join(", "), new_key);
+                    raws[i].key = new_key;
+                }
+                continue;
+            }
+        };
+        for i in members.into_iter().filter(|&i| i != kept) {
+            eprintln!("{} Key conflict on {}: dropped entry from {}", constants::WARN.yellow(), key, raws[i].sources.join(", "));
+            dropped[i] = true;
+        }
+    }
+
+    raws.into_iter().zip(dropped).filter(|(_, dropped)| !dropped).map(|(raw, _)| raw).collect()
+}
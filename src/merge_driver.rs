@@ -0,0 +1,260 @@
+use std::{collections::HashMap, fs, io};
+use colored::Colorize;
+
+use crate::bibfile::{self, Block, RawEntry};
+use crate::constants;
+use crate::diff;
+use crate::printer::{self, Field, Format, Value};
+
+/// Three-way merge of `ours` with `theirs` against their common `base`, usable as a git
+/// merge driver (`bibliohelp merge-driver %O %A %B`). Entries and fields changed on one
+/// side only are taken from that side; fields changed on both sides get conflict markers.
+/// The text of `ours` is kept as written: comments, `@string`, `@preamble` and `@comment`
+/// blocks and untouched entries are copied byte for byte, and only the entries whose fields
+/// were merged are printed again. The result is written to `ours` and the number of
+/// conflicting fields is returned.
+pub fn run(base_file: &str, ours_file: &str, theirs_file: &str, format: &Format) -> io::Result<usize> {
+    let (merged, conflicts) = merge(&fs::read_to_string(base_file)?, &fs::read_to_string(ours_file)?, &fs::read_to_string(theirs_file)?, format)?;
+    fs::write(ours_file, merged)?;
+    Ok(conflicts)
+}
+
+/// The merged text of `ours` and `theirs`, and the number of conflicting fields.
+fn merge(base: &str, ours: &str, theirs: &str, format: &Format) -> io::Result<(String, usize)> {
+    let base = bibfile::parse_entries(base);
+    let ours_blocks = bibfile::parse_document(ours);
+    let theirs = bibfile::parse_entries(theirs);
+
+    let (base_specials, base): (Vec<RawEntry>, Vec<RawEntry>) = base.into_iter().partition(RawEntry::is_special);
+    let (theirs_specials, theirs): (Vec<RawEntry>, Vec<RawEntry>) = theirs.into_iter().partition(RawEntry::is_special);
+    let ours: Vec<&RawEntry> = ours_blocks.iter().filter_map(|block| match block {
+        Block::Entry(entry) if !entry.is_special() => Some(entry),
+        _ => None,
+    }).collect();
+
+    let base_index = index(base.iter());
+    let theirs_index = index(theirs.iter());
+    let ours_ids = ids(ours.iter().copied());
+    let ours_index: HashMap<(&str, usize), &RawEntry> = ours_ids.iter().copied().zip(ours.iter().copied()).collect();
+
+    let mut output = String::new();
+    let mut conflicts = 0;
+    let mut ours_ids = ours_ids.into_iter();
+
+    for block in &ours_blocks {
+        let entry = match block {
+            Block::Text(text) => {
+                output.push_str(text);
+                continue;
+            }
+            Block::Entry(entry) if entry.is_special() => {
+                // Dropped when the other side deleted it.
+                let deleted = !has_text(&theirs_specials, &entry.text) && has_text(&base_specials, &entry.text);
+                if !deleted {
+                    output.push_str(&entry.text);
+                }
+                continue;
+            }
+            Block::Entry(entry) => entry,
+        };
+        let id = ours_ids.next().unwrap();
+        let base_entry = base_index.get(&id).copied();
+        match theirs_index.get(&id).copied() {
+            Some(theirs_entry) => {
+                if same_entry(entry, theirs_entry) || base_entry.is_some_and(|base_entry| same_entry(base_entry, theirs_entry)) {
+                    output.push_str(&entry.text);
+                } else if base_entry.is_some_and(|base_entry| same_entry(base_entry, entry)) {
+                    output.push_str(&theirs_entry.text);
+                } else {
+                    let (entry_type, fields, count) = merge_entry(base_entry, entry, theirs_entry);
+                    conflicts += count;
+                    let mut rendered = Vec::new();
+                    printer::write_entry(&mut rendered, &entry_type, id.0, &fields, format)?;
+                    output.push_str(String::from_utf8_lossy(&rendered).trim_end());
+                }
+            }
+            None => match base_entry {
+                Some(base_entry) if same_entry(base_entry, entry) => {}
+                Some(_) => {
+                    eprintln!("{} Entry {} was deleted on one side and modified on the other, keeping it", constants::WARN.yellow(), id.0);
+                    output.push_str(&entry.text);
+                }
+                None => output.push_str(&entry.text),
+            },
+        }
+    }
+
+    // Blocks and entries only found on their side are added at the end.
+    let mut added = Vec::new();
+    for special in &theirs_specials {
+        if !has_text(&base_specials, &special.text) && !ours_blocks.iter().any(|block| matches!(block, Block::Entry(entry) if entry.text == special.text)) {
+            added.push(special.text.as_str());
+        }
+    }
+    for (id, entry) in ids(theirs.iter()).into_iter().zip(&theirs) {
+        if ours_index.contains_key(&id) {
+            continue;
+        }
+        match base_index.get(&id) {
+            Some(base_entry) if same_entry(base_entry, entry) => {}
+            Some(_) => {
+                eprintln!("{} Entry {} was deleted on one side and modified on the other, keeping it", constants::WARN.yellow(), id.0);
+                added.push(entry.text.as_str());
+            }
+            None => added.push(entry.text.as_str()),
+        }
+    }
+    for text in added {
+        if !output.is_empty() && !output.ends_with("\n\n") {
+            output.push_str(if output.ends_with('\n') { "\n" } else { "\n\n" });
+        }
+        output.push_str(text);
+        output.push('\n');
+    }
+
+    Ok((output, conflicts))
+}
+
+fn has_text(blocks: &[RawEntry], text: &str) -> bool {
+    blocks.iter().any(|block| block.text == text)
+}
+
+/// Identifies entries by key and occurrence, so that duplicated keys are matched in order.
+fn ids<'a>(entries: impl Iterator<Item = &'a RawEntry>) -> Vec<(&'a str, usize)> {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    entries.map(|entry| {
+        let count = seen.entry(entry.key.as_str()).or_insert(0);
+        *count += 1;
+        (entry.key.as_str(), *count)
+    }).collect()
+}
+
+fn index<'a>(entries: impl Iterator<Item = &'a RawEntry> + Clone) -> HashMap<(&'a str, usize), &'a RawEntry> {
+    ids(entries.clone()).into_iter().zip(entries).collect()
+}
+
+fn merge_entry(base: Option<&RawEntry>, ours: &RawEntry, theirs: &RawEntry) -> (String, Vec<Field>, usize) {
+    let mut conflicts = 0;
+
+    let base_type = base.map(|b| b.entry_type.as_str());
+    let entry_type = if ours.entry_type == theirs.entry_type || base_type == Some(theirs.entry_type.as_str()) {
+        ours.entry_type.to_string()
+    } else if base_type == Some(ours.entry_type.as_str()) {
+        theirs.entry_type.to_string()
+    } else {
+        eprintln!("{} Entry {} changed type on both sides, keeping @{}", constants::WARN.yellow(), ours.key, ours.entry_type);
+        conflicts += 1;
+        ours.entry_type.to_string()
+    };
+
+    let mut names: Vec<&str> = ours.fields.iter().map(|(n, _)| n.as_str()).collect();
+    names.extend(theirs.fields.iter().map(|(n, _)| n.as_str()).filter(|n| ours.get(n).is_none()));
+
+    let mut fields = Vec::new();
+    for name in names {
+        let base_value = base.and_then(|b| b.get(name));
+        let ours_value = ours.get(name);
+        let theirs_value = theirs.get(name);
+
+        let merged = if same_value(ours_value, theirs_value) || same_value(theirs_value, base_value) {
+            ours_value.map(|_| ours)
+        } else if same_value(ours_value, base_value) {
+            theirs_value.map(|_| theirs)
+        } else {
+            conflicts += 1;
+            let marked = format!("\n<<<<<<< ours\n{}\n=======\n{}\n>>>>>>> theirs\n", ours.raw_value(name).unwrap_or(""), theirs.raw_value(name).unwrap_or(""));
+            fields.push((name.to_string(), Value::Macro(marked)));
+            continue;
+        };
+        // Values are written back as they were read, delimiters and protecting braces included.
+        if let Some(side) = merged {
+            fields.extend(printer::verbatim(side, name).map(|value| (name.to_string(), value)));
+        }
+    }
+
+    (entry_type, fields, conflicts)
+}
+
+fn same_value(a: Option<&str>, b: Option<&str>) -> bool {
+    a.map(diff::normalize_value) == b.map(diff::normalize_value)
+}
+
+fn same_entry(a: &RawEntry, b: &RawEntry) -> bool {
+    a.entry_type == b.entry_type
+        && a.fields.len() == b.fields.len()
+        && a.fields.iter().all(|(name, value)| same_value(Some(value), b.get(name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "% comment
+@string{jcomp = \"The Computer Journal\"}
+
+@article{knuth1984,
+    author = {Donald E. Knuth},
+    title = {{Literate Programming with DNA}},
+    journal = jcomp,
+    year = {1984}
+}
+
+@book{lamport1994,
+    author = {Leslie Lamport},
+    title = {{LaTeX}},
+    year = {1994}
+}
+";
+
+    fn merge_texts(ours: &str, theirs: &str) -> (String, usize) {
+        merge(BASE, ours, theirs, &Format::default()).unwrap()
+    }
+
+    #[test]
+    fn change_on_one_side() {
+        let theirs = BASE.replace("year = {1994}", "year = {1995}");
+        let (merged, conflicts) = merge_texts(BASE, &theirs);
+        assert_eq!(conflicts, 0);
+        assert_eq!(merged, theirs);
+    }
+
+    #[test]
+    fn changes_to_different_fields() {
+        let ours = BASE.replace("year = {1984}", "year = {1985}");
+        let theirs = BASE.replace("    journal = jcomp,\n", "    journal = jcomp,\n    note = {{Reprinted}},\n");
+        let (merged, conflicts) = merge_texts(&ours, &theirs);
+        assert_eq!(conflicts, 0);
+        assert!(merged.starts_with("% comment\n@string{jcomp = \"The Computer Journal\"}\n"));
+        assert!(merged.contains("{{Literate Programming with DNA}},"));
+        assert!(merged.contains("= jcomp,"));
+        assert!(merged.contains("= {1985},"));
+        assert!(merged.contains("= {{Reprinted}}"));
+        assert!(merged.contains("@book{lamport1994,\n    author = {Leslie Lamport},"));
+    }
+
+    #[test]
+    fn conflicting_field() {
+        let ours = BASE.replace("year = {1984}", "year = {1985}");
+        let theirs = BASE.replace("year = {1984}", "year = {1986}");
+        let (merged, conflicts) = merge_texts(&ours, &theirs);
+        assert_eq!(conflicts, 1);
+        assert!(merged.contains("\n<<<<<<< ours\n{1985}\n=======\n{1986}\n>>>>>>> theirs\n"));
+    }
+
+    #[test]
+    fn delete_and_modify() {
+        let start = BASE.find("@book").unwrap();
+        let deleted = BASE[..start].trim_end().to_string() + "\n";
+        let modified = BASE.replace("year = {1994}", "year = {1995}");
+
+        let (merged, conflicts) = merge_texts(&deleted, &modified);
+        assert_eq!(conflicts, 0);
+        assert!(merged.contains("year = {1995}"));
+
+        let (merged, _) = merge_texts(&modified, &deleted);
+        assert!(merged.contains("year = {1995}"));
+
+        let (merged, _) = merge_texts(BASE, &deleted);
+        assert!(!merged.contains("lamport1994"));
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use regex::Regex;
+use colored::Colorize;
+
+use crate::constants;
+use crate::month::Month;
+use crate::names::{self, Name};
+use crate::printer::{Field, Value};
+use crate::tex;
+
+pub struct Misc {
+    author: Vec<Name>,
+    title: String,
+    howpublished: String,
+    url: String,
+    month: Option<Month>,
+    year: i32
+}
+
+impl Misc {
+    pub fn new(fields: &HashMap<String, String>) -> Result<Misc, String> {
+        let author = match fields.get("author") {
+            Some(s) if !s.is_empty() => s.to_string(),
+            Some(_) => String::new(),
+            None => String::new(),
+        };
+        let title = fields.get("title").and_then(|s| if !s.is_empty() { Some(s) } else { None }).ok_or("Missing or empty title field")?.to_string();
+        let mut howpublished = match fields.get("howpublished") {
+            Some(s) if !s.is_empty() => s.to_string(),
+            Some(_) => String::new(),
+            None => String::new(),
+        };
+        let mut url = match fields.get("url") {
+            Some(s) if !s.is_empty() => s.to_string(),
+            Some(_) => String::new(),
+            None => String::new(),
+        };
+        let month = match fields.get("month") {
+            Some(s) if !s.is_empty() => s.to_string(),
+            Some(_) => String::new(),
+            None => String::new(),
+        };
+        let year = fields.get("year").and_then(|s| if !s.is_empty() { Some(s) } else { None }).ok_or("Missing or empty year field")?.to_string();
+
+        if url.is_empty() && howpublished.starts_with("\\url{") {
+            url = howpublished.trim_start_matches("\\url{").trim_end_matches('}').to_string();
+            howpublished = String::new();
+        }
+
+        let title_re = Regex::new(constants::REGEX_TITLE).unwrap();
+
+        let author = match author.is_empty() {
+            true => Vec::new(),
+            false => names::parse_list(&author).map_err(|e| format!("{} Invalid authors format: {}", constants::ERR.red(), e))?,
+        };
+
+        if !title_re.is_match(&tex::to_plain(&title)) {
+            return Err(format!("{} Invalid title format", constants::ERR.red()));
+        }
+
+        let month = match month.is_empty() {
+            true => None,
+            false => Some(Month::parse(&month).map_err(|e| format!("{} Invalid month format: {}", constants::ERR.red(), e))?),
+        };
+
+        let year: i32 = match year.parse() {
+            Ok(y) => y,
+            Err(_) => return Err(format!("{} Invalid year format", constants::ERR.red())),
+        };
+
+        Ok(Misc {
+            author,
+            title,
+            howpublished,
+            url,
+            month,
+            year
+        })
+    }
+
+    pub fn fields(&self) -> Vec<Field> {
+        vec![
+            ("author".to_string(), Value::Text(names::format_list(&self.author))),
+            ("title".to_string(), Value::Protected(self.title.clone())),
+            ("howpublished".to_string(), Value::Text(self.howpublished.clone())),
+            ("url".to_string(), Value::Text(self.url.clone())),
+            ("month".to_string(), Value::Macro(self.month.map(|month| month.to_bibtex()).unwrap_or_default())),
+            ("year".to_string(), Value::Text(self.year.to_string())),
+        ].into_iter().filter(|(_, value)| !value.as_str().is_empty()).collect()
+    }
+}
@@ -0,0 +1,128 @@
+use crate::constants;
+use crate::tex;
+
+/// Month names in English, German, French, Spanish, Italian, Dutch and Portuguese.
+const NAMES: [[&str; 12]; 7] = [
+    ["january", "february", "march", "april", "may", "june", "july", "august", "september", "october", "november", "december"],
+    ["januar", "februar", "märz", "april", "mai", "juni", "juli", "august", "september", "oktober", "november", "dezember"],
+    ["janvier", "février", "mars", "avril", "mai", "juin", "juillet", "août", "septembre", "octobre", "novembre", "décembre"],
+    ["enero", "febrero", "marzo", "abril", "mayo", "junio", "julio", "agosto", "septiembre", "octubre", "noviembre", "diciembre"],
+    ["gennaio", "febbraio", "marzo", "aprile", "maggio", "giugno", "luglio", "agosto", "settembre", "ottobre", "novembre", "dicembre"],
+    ["januari", "februari", "maart", "april", "mei", "juni", "juli", "augustus", "september", "oktober", "november", "december"],
+    ["janeiro", "fevereiro", "março", "abril", "maio", "junho", "julho", "agosto", "setembro", "outubro", "novembro", "dezembro"]
+];
+
+/// Shortest abbreviation accepted for a month name, so that "ma" or "ju" are not guessed.
+const MIN_ABBREVIATION: usize = 3;
+
+/// A month, or a range of months such as June/July, numbered from 1.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Month {
+    Single(usize),
+    Range(usize, usize)
+}
+
+impl Month {
+    /// Reads a month written as a number (`9`, `09`), a full, abbreviated or localised name
+    /// (`September`, `Sept.`, `{September}`, `septembre`), the BibTeX macro (`sep`) or a range
+    /// of those separated by `/`, `-`, `--`, `&`, `to` or `and` (`June/July`, `jun # "/" # jul`).
+    pub fn parse(text: &str) -> Result<Month, String> {
+        let mut clean = tex::to_plain(text).replace(['#', '"'], " ").to_lowercase();
+        for separator in ["--", "–", "-", "&", " to ", " and "] {
+            clean = clean.replace(separator, "/");
+        }
+        let parts: Vec<&str> = clean.split('/').map(str::trim).collect();
+        let invalid = || format!("{} is not a month or a range of months", text.trim());
+
+        match parts.as_slice() {
+            [single] => Ok(Month::Single(number(single).ok_or_else(invalid)?)),
+            [first, last] => {
+                let (first, last) = (number(first).ok_or_else(invalid)?, number(last).ok_or_else(invalid)?);
+                Ok(if first == last { Month::Single(first) } else { Month::Range(first, last) })
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    /// The month as a BibTeX macro, ranges being concatenated as in `jun # "/" # jul`.
+    pub fn to_bibtex(self) -> String {
+        match self {
+            Month::Single(month) => constants::MONTHS[month - 1].to_string(),
+            Month::Range(first, last) => format!("{} # \"/\" # {}", constants::MONTHS[first - 1], constants::MONTHS[last - 1]),
+        }
+    }
+
+    /// The month as part of a biblatex date: `2020-09`, or `2020-06/2020-07` for a range.
+    pub fn to_date(self, year: &str) -> String {
+        match self {
+            Month::Single(month) => format!("{}-{:02}", year, month),
+            Month::Range(first, last) => format!("{}-{:02}/{}-{:02}", year, first, year, last),
+        }
+    }
+}
+
+/// The number of a single month, or None when `text` is not understood or could be several months.
+fn number(text: &str) -> Option<usize> {
+    let text = text.trim_end_matches('.').trim();
+    if let Ok(n) = text.parse::<usize>() {
+        return (1..=12).contains(&n).then_some(n);
+    }
+    if text.chars().count() < MIN_ABBREVIATION {
+        return None;
+    }
+
+    let mut found = None;
+    for names in NAMES {
+        for (i, name) in names.iter().enumerate() {
+            if name.starts_with(text) {
+                match found {
+                    Some(month) if month != i + 1 => return None,
+                    _ => found = Some(i + 1),
+                }
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Option<Month> {
+        Month::parse(text).ok()
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(parse("9"), Some(Month::Single(9)));
+        assert_eq!(parse("09"), Some(Month::Single(9)));
+        assert!(parse("13").is_none());
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(parse("Sept."), Some(Month::Single(9)));
+        assert_eq!(parse("{September}"), Some(Month::Single(9)));
+        assert_eq!(parse("sep"), Some(Month::Single(9)));
+        assert_eq!(parse("septembre"), Some(Month::Single(9)));
+        assert!(parse("ma").is_none());
+        assert_eq!(parse("mar"), Some(Month::Single(3)));
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(parse("June/July"), Some(Month::Range(6, 7)));
+        assert_eq!(parse("jun # \"/\" # jul"), Some(Month::Range(6, 7)));
+        assert_eq!(parse("June--July"), Some(Month::Range(6, 7)));
+        assert_eq!(parse("June and July"), Some(Month::Range(6, 7)));
+        assert_eq!(parse("June/June"), Some(Month::Single(6)));
+    }
+
+    #[test]
+    fn output() {
+        assert_eq!(Month::Range(6, 7).to_bibtex(), "jun # \"/\" # jul");
+        assert_eq!(Month::Single(9).to_date("2020"), "2020-09");
+        assert_eq!(Month::Range(6, 7).to_date("2020"), "2020-06/2020-07");
+    }
+}
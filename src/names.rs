@@ -0,0 +1,432 @@
+use std::collections::HashMap;
+use colored::Colorize;
+use regex::Regex;
+
+use crate::constants;
+use crate::tex;
+
+/// A person (or a braced corporate author) split into the four parts BibTeX knows about.
+#[derive(Clone, PartialEq)]
+pub struct Name {
+    pub first: String,
+    pub von: String,
+    pub last: String,
+    pub jr: String
+}
+
+/// How `--name-style` writes each name of an author or editor list.
+#[derive(Clone, Copy, PartialEq)]
+pub enum NameStyle {
+    LastFirst,
+    FirstLast
+}
+
+impl NameStyle {
+    pub fn parse(name: &str) -> Result<NameStyle, String> {
+        match name {
+            "last-first" => Ok(NameStyle::LastFirst),
+            "first-last" => Ok(NameStyle::FirstLast),
+            _ => Err(format!("Unknown name style: {} (expected last-first or first-last)", name)),
+        }
+    }
+}
+
+/// What `--initials` does with first names: leave them, reduce them to initials,
+/// or replace initials with the full first name found elsewhere in the bibliography.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Initials {
+    Keep,
+    Abbreviate,
+    Expand
+}
+
+impl Initials {
+    pub fn parse(name: &str) -> Result<Initials, String> {
+        match name {
+            "keep" => Ok(Initials::Keep),
+            "abbreviate" => Ok(Initials::Abbreviate),
+            "expand" => Ok(Initials::Expand),
+            _ => Err(format!("Unknown initials mode: {} (expected keep, abbreviate or expand)", name)),
+        }
+    }
+}
+
+enum Token {
+    Word(String),
+    Comma
+}
+
+impl Name {
+    /// Splits one name written as "First von Last", "von Last, First" or "von Last, Jr, First".
+    pub fn parse(name: &str) -> Result<Name, String> {
+        let tokens = tokenize(name)?;
+        let mut parts: Vec<Vec<String>> = vec![Vec::new()];
+        for token in tokens {
+            match token {
+                Token::Word(word) => parts.last_mut().unwrap().push(word),
+                Token::Comma => parts.push(Vec::new()),
+            }
+        }
+
+        let name = match parts.len() {
+            1 => {
+                let words = &parts[0];
+                let n = words.len();
+                match (0..n.saturating_sub(1)).find(|&i| is_lowercase(&words[i])) {
+                    Some(start) => {
+                        let end = (start..n - 1).rev().find(|&i| is_lowercase(&words[i])).unwrap();
+                        Name {
+                            first: words[..start].join(" "),
+                            von: words[start..=end].join(" "),
+                            last: words[end + 1..].join(" "),
+                            jr: String::new()
+                        }
+                    }
+                    None => Name {
+                        first: words[..n.saturating_sub(1)].join(" "),
+                        von: String::new(),
+                        last: words.last().cloned().unwrap_or_default(),
+                        jr: String::new()
+                    },
+                }
+            }
+            2 | 3 => {
+                let (von, last) = split_von_last(&parts[0]);
+                let (jr, first) = if parts.len() == 3 { (&parts[1], &parts[2]) } else { (&Vec::new(), &parts[1]) };
+                Name { first: first.join(" "), von, last, jr: jr.join(" ") }
+            }
+            _ => return Err(format!("Too many commas in name: {}", name)),
+        };
+
+        if name.last.is_empty() {
+            return Err("Empty last name".to_string());
+        }
+        Ok(name)
+    }
+
+    /// The name in BibTeX's unambiguous "von Last, Jr, First" form.
+    pub fn to_bibtex(&self) -> String {
+        let mut name = [self.von.as_str(), self.last.as_str()].iter().filter(|p| !p.is_empty()).copied().collect::<Vec<&str>>().join(" ");
+        if !self.jr.is_empty() {
+            name = format!("{}, {}", name, self.jr);
+        }
+        if !self.first.is_empty() {
+            name = format!("{}, {}", name, self.first);
+        }
+        name
+    }
+
+    /// The name as "First von Last", or in "von Last, Jr, First" form when reading it
+    /// back would split it differently (a Jr part, a multi-word last name, lowercase first names).
+    pub fn to_first_last(&self) -> String {
+        let ambiguous = !self.jr.is_empty()
+            || split_words(&self.last).map(|words| words.len() > 1).unwrap_or(true)
+            || split_words(&self.first).unwrap_or_default().iter().any(|word| is_lowercase(word))
+            || split_words(&self.von).unwrap_or_default().iter().any(|word| !is_lowercase(word));
+        if ambiguous {
+            return self.to_bibtex();
+        }
+        [self.first.as_str(), self.von.as_str(), self.last.as_str()].iter().filter(|p| !p.is_empty()).copied().collect::<Vec<&str>>().join(" ")
+    }
+
+    pub fn is_others(&self) -> bool {
+        self.last == "others" && self.first.is_empty() && self.von.is_empty() && self.jr.is_empty()
+    }
+}
+
+/// Parses an `author` or `editor` field, whose names are separated by "and".
+pub fn parse_list(list: &str) -> Result<Vec<Name>, String> {
+    let name_re = Regex::new(constants::REGEX_NAME).unwrap();
+    if !name_re.is_match(&tex::to_plain(list)) {
+        return Err(format!("Unexpected characters in names: {}", list));
+    }
+
+    let mut names = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    for word in split_words(list)?.into_iter().chain(["and".to_string()]) {
+        if word.eq_ignore_ascii_case("and") {
+            if current.is_empty() {
+                return Err(format!("Empty name in list: {}", list));
+            }
+            names.push(Name::parse(&current.join(" "))?);
+            current.clear();
+        } else {
+            current.push(word);
+        }
+    }
+
+    Ok(names)
+}
+
+/// Joins names back into a field value, each in "von Last, Jr, First" form.
+pub fn format_list(names: &[Name]) -> String {
+    names.iter().map(Name::to_bibtex).collect::<Vec<String>>().join(" and ")
+}
+
+/// Full first names seen in the bibliography, by von and last name, used to expand initials.
+pub struct FirstNames(HashMap<String, Vec<String>>);
+
+impl FirstNames {
+    pub fn collect<'a, I: IntoIterator<Item = &'a str>>(lists: I) -> FirstNames {
+        let mut first_names: HashMap<String, Vec<String>> = HashMap::new();
+        for name in lists.into_iter().flat_map(|list| parse_list(list).unwrap_or_default()) {
+            let first = initials_split(&name.first).join(" ");
+            if first.is_empty() || first.split(' ').all(is_initial) {
+                continue;
+            }
+            let known = first_names.entry(family(&name)).or_default();
+            if !known.contains(&first) {
+                known.push(first);
+            }
+        }
+        FirstNames(first_names)
+    }
+
+    /// The only known full first name whose words match the initials of `name`.
+    fn expand(&self, name: &Name) -> Option<String> {
+        let words = initials_split(&name.first);
+        if !words.iter().any(|word| is_initial(word)) {
+            return None;
+        }
+        let candidates: Vec<&String> = self.0.get(&family(name))?.iter().filter(|full| {
+            let full: Vec<&str> = full.split(' ').collect();
+            full.len() == words.len() && words.iter().zip(&full).all(|(word, full)| match is_initial(word) {
+                true => abbreviate(full) == *word,
+                false => word == full,
+            })
+        }).collect();
+        match candidates.as_slice() {
+            [full] => Some(full.to_string()),
+            [] => None,
+            _ => {
+                eprintln!("{} Cannot expand initials of {}: {} match", constants::WARN.yellow(), name.to_bibtex(),
+                    candidates.iter().map(|c| c.as_str()).collect::<Vec<&str>>().join(", "));
+                None
+            }
+        }
+    }
+}
+
+/// Rewrites a name list in the given style, with " and " between names.
+pub fn restyle(list: &str, style: NameStyle, initials: Initials, first_names: &FirstNames) -> String {
+    let Ok(names) = parse_list(list) else { return list.to_string() };
+    names.into_iter().map(|mut name| {
+        match initials {
+            Initials::Keep => {}
+            Initials::Abbreviate => name.first = initials_split(&name.first).iter().map(|word| abbreviate(word)).collect::<Vec<String>>().join(" "),
+            Initials::Expand => {
+                name.first = first_names.expand(&name).unwrap_or_else(|| initials_split(&name.first).join(" "));
+            }
+        }
+        match style {
+            _ if name.is_others() => name.last,
+            NameStyle::LastFirst => name.to_bibtex(),
+            NameStyle::FirstLast => name.to_first_last(),
+        }
+    }).collect::<Vec<String>>().join(" and ")
+}
+
+fn family(name: &Name) -> String {
+    format!("{} {}", name.von, name.last).trim().to_lowercase()
+}
+
+/// First name words, with run-together initials such as "D.E." split and bare letters given a period.
+fn initials_split(first: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for word in split_words(first).unwrap_or_default() {
+        if !word.contains('{') && word.contains('.') && word.split_inclusive('.').all(is_initial) {
+            words.extend(word.split_inclusive('.').map(str::to_string));
+        } else if !word.contains('{') && word.chars().count() == 1 {
+            words.push(format!("{}.", word));
+        } else {
+            words.push(word);
+        }
+    }
+    words
+}
+
+fn is_initial(word: &str) -> bool {
+    word.ends_with('.') && word.split('-').all(|part| part.trim_end_matches('.').chars().count() == 1 || part.starts_with('{'))
+}
+
+/// "Donald" becomes "D.", "Jean-Paul" becomes "J.-P." and `{\"O}zlem` becomes `{\"O}.`.
+fn abbreviate(word: &str) -> String {
+    if is_initial(word) {
+        return word.to_string();
+    }
+    word.split('-').map(|part| {
+        let letter = match part.starts_with('{') {
+            true => &part[..part.find('}').map(|i| i + 1).unwrap_or(part.len())],
+            false => part.char_indices().nth(1).map(|(i, _)| &part[..i]).unwrap_or(part),
+        };
+        format!("{}.", letter)
+    }).collect::<Vec<String>>().join("-")
+}
+
+/// Von and last names of every author (or editor), skipping "others".
+pub fn last_names(list: &str) -> Vec<String> {
+    parse_list(list).unwrap_or_default()
+        .into_iter()
+        .filter(|name| !name.is_others())
+        .map(|name| format!("{} {}", name.von, name.last).trim().to_string())
+        .collect()
+}
+
+/// Whitespace-separated words at brace depth 0, keeping braced groups whole.
+fn split_words(text: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut depth = 0;
+
+    for c in text.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Err(format!("Unbalanced braces in names: {}", text)),
+            '}' => depth -= 1,
+            c if depth == 0 && (c.is_whitespace() || c == '~') => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        word.push(c);
+    }
+    if depth != 0 {
+        return Err(format!("Unbalanced braces in names: {}", text));
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    Ok(words)
+}
+
+fn tokenize(name: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    for word in split_words(name)? {
+        let mut piece = String::new();
+        let mut depth = 0;
+        for c in word.chars() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                ',' if depth == 0 => {
+                    if !piece.is_empty() {
+                        tokens.push(Token::Word(std::mem::take(&mut piece)));
+                    }
+                    tokens.push(Token::Comma);
+                    continue;
+                }
+                _ => {}
+            }
+            piece.push(c);
+        }
+        if !piece.is_empty() {
+            tokens.push(Token::Word(piece));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Splits the part before the first comma: every word up to the last lowercase one
+/// is the von part, but the final word always belongs to the last name.
+fn split_von_last(words: &[String]) -> (String, String) {
+    let n = words.len();
+    match (0..n.saturating_sub(1)).rev().find(|&i| is_lowercase(&words[i])) {
+        Some(end) => (words[..=end].join(" "), words[end + 1..].join(" ")),
+        None => (String::new(), words.join(" ")),
+    }
+}
+
+/// Whether the first letter at brace depth 0 is lowercase. A leading special character
+/// such as `{\"o}` takes the case of the letter it accents; other braced groups count as uppercase.
+fn is_lowercase(word: &str) -> bool {
+    let mut chars = word.chars().peekable();
+    if word.starts_with("{\\") {
+        chars.next();
+        chars.next();
+        let command: String = chars.by_ref().take_while(|c| c.is_alphabetic()).collect();
+        let letter = chars.take_while(|&c| c != '}').find(|c| c.is_alphabetic());
+        return match letter.or(command.chars().next()) {
+            Some(c) => c.is_lowercase(),
+            None => false,
+        };
+    }
+
+    let mut depth = 0;
+    for c in chars {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            c if depth == 0 && c.is_alphabetic() => return c.is_lowercase(),
+            _ => {}
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(name: &Name) -> (&str, &str, &str, &str) {
+        (&name.first, &name.von, &name.last, &name.jr)
+    }
+
+    #[test]
+    fn first_last() {
+        let name = Name::parse("Donald E. Knuth").unwrap();
+        assert_eq!(parts(&name), ("Donald E.", "", "Knuth", ""));
+        assert_eq!(name.to_bibtex(), "Knuth, Donald E.");
+    }
+
+    #[test]
+    fn von_part() {
+        let name = Name::parse("van der Berg, Jan").unwrap();
+        assert_eq!(parts(&name), ("Jan", "van der", "Berg", ""));
+        assert_eq!(name.to_first_last(), "Jan van der Berg");
+
+        let name = Name::parse("Ludwig van Beethoven").unwrap();
+        assert_eq!(parts(&name), ("Ludwig", "van", "Beethoven", ""));
+    }
+
+    #[test]
+    fn jr_part() {
+        let name = Name::parse("King, Jr., Martin Luther").unwrap();
+        assert_eq!(parts(&name), ("Martin Luther", "", "King", "Jr."));
+        assert_eq!(name.to_bibtex(), "King, Jr., Martin Luther");
+    }
+
+    #[test]
+    fn corporate_author() {
+        let name = Name::parse("{World Health Organization}").unwrap();
+        assert_eq!(parts(&name), ("", "", "{World Health Organization}", ""));
+        assert_eq!(name.to_first_last(), "{World Health Organization}");
+    }
+
+    #[test]
+    fn lists() {
+        let names = parse_list("Donald E. Knuth and van der Berg, Jan and others").unwrap();
+        assert_eq!(names.len(), 3);
+        assert_eq!(names[1].last, "Berg");
+        assert!(names[2].is_others());
+        assert_eq!(format_list(&names), "Knuth, Donald E. and van der Berg, Jan and others");
+    }
+
+    #[test]
+    fn restyle_lists() {
+        let none = FirstNames::collect([]);
+        let list = "Knuth, Donald Ervin and van der Berg, Jan";
+        assert_eq!(restyle(list, NameStyle::FirstLast, Initials::Keep, &none), "Donald Ervin Knuth and Jan van der Berg");
+        assert_eq!(restyle(list, NameStyle::LastFirst, Initials::Abbreviate, &none), "Knuth, D. E. and van der Berg, J.");
+    }
+
+    #[test]
+    fn expand_initials() {
+        let known = FirstNames::collect(["Knuth, Donald Ervin"]);
+        assert_eq!(restyle("D. E. Knuth", NameStyle::LastFirst, Initials::Expand, &known), "Knuth, Donald Ervin");
+        assert_eq!(restyle("J. Knuth", NameStyle::LastFirst, Initials::Expand, &known), "Knuth, J.");
+    }
+}
@@ -0,0 +1,187 @@
+use std::fs;
+
+use crate::bibliography::InvalidPolicy;
+use crate::case::{CaseRules, TitleCase};
+use crate::dedup::DedupMode;
+use crate::dialect::Dialect;
+use crate::diff::MatchBy;
+use crate::keygen::{KeyPattern, KeyPolicy};
+use crate::merge::ConflictStrategy;
+use crate::names::{Initials, NameStyle};
+use crate::printer::Format;
+use crate::protect::{self, Protection};
+use crate::sort::SortOrder;
+use crate::split::SplitBy;
+
+/// Per-project defaults, one `option = value` (or bare `option`) per line.
+pub const CONFIG_FILE: &str = ".bibliohelp";
+pub const DEFAULT_OUTPUT: &str = "biblio.bib";
+
+pub struct Options {
+    pub inputs: Vec<String>,
+    pub output: Option<String>,
+    pub dialect: Option<Dialect>,
+    pub inline_crossref: bool,
+    pub key_pattern: KeyPattern,
+    pub key_policy: KeyPolicy,
+    pub dry_run: bool,
+    pub dedup: Option<DedupMode>,
+    pub on_conflict: ConflictStrategy,
+    pub split_by: SplitBy,
+    pub match_by: MatchBy,
+    pub sort: Option<SortOrder>,
+    pub reverse: bool,
+    pub format: Format,
+    pub lossless: bool,
+    pub invalid: Option<InvalidPolicy>,
+    pub name_style: NameStyle,
+    pub initials: Initials,
+    pub fix_escapes: bool,
+    pub protection: Protection,
+    pub protect_words: Vec<String>,
+    pub title_case: Option<TitleCase>,
+    pub case_rules: CaseRules
+}
+
+impl Options {
+    pub fn output(&self) -> &str {
+        self.output.as_deref().unwrap_or(DEFAULT_OUTPUT)
+    }
+
+    /// Parses `args` on top of the options found in the project's config file.
+    pub fn load(args: &[String]) -> Result<Options, String> {
+        let mut all = match fs::read_to_string(CONFIG_FILE) {
+            Ok(content) => config_args(&content),
+            Err(_) => Vec::new(),
+        };
+        all.extend_from_slice(args);
+        Options::parse(&all)
+    }
+
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options {
+            inputs: Vec::new(),
+            output: None,
+            dialect: None,
+            inline_crossref: false,
+            key_pattern: KeyPattern::default(),
+            key_policy: KeyPolicy::Keep,
+            dry_run: false,
+            dedup: None,
+            on_conflict: ConflictStrategy::Suffix,
+            split_by: SplitBy::Type,
+            match_by: MatchBy::Key,
+            sort: None,
+            reverse: false,
+            format: Format::default(),
+            lossless: false,
+            invalid: None,
+            name_style: NameStyle::LastFirst,
+            initials: Initials::Keep,
+            fix_escapes: false,
+            protection: Protection::All,
+            protect_words: Vec::new(),
+            title_case: None,
+            case_rules: CaseRules::default()
+        };
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "-o" | "--output" => {
+                    options.output = Some(iter.next().ok_or("Missing value for --output")?.to_string());
+                }
+                "--to" => {
+                    let value = iter.next().ok_or("Missing value for --to")?;
+                    options.dialect = Some(Dialect::parse(value)?);
+                }
+                "--inline-crossref" => options.inline_crossref = true,
+                "--key-pattern" => {
+                    let value = iter.next().ok_or("Missing value for --key-pattern")?;
+                    options.key_pattern = KeyPattern::parse(value)?;
+                }
+                "--dedup" => {
+                    let value = iter.next().ok_or("Missing value for --dedup")?;
+                    options.dedup = Some(DedupMode::parse(value)?);
+                }
+                "--on-conflict" => {
+                    let value = iter.next().ok_or("Missing value for --on-conflict")?;
+                    options.on_conflict = ConflictStrategy::parse(value)?;
+                }
+                "--by" => {
+                    let value = iter.next().ok_or("Missing value for --by")?;
+                    options.split_by = SplitBy::parse(value)?;
+                }
+                "--match" => {
+                    let value = iter.next().ok_or("Missing value for --match")?;
+                    options.match_by = MatchBy::parse(value)?;
+                }
+                "--sort" => {
+                    let value = iter.next().ok_or("Missing value for --sort")?;
+                    options.sort = Some(SortOrder::parse(value)?);
+                }
+                "--reverse" => options.reverse = true,
+                "--field-order" | "--align" | "--indent" | "--trailing-comma" | "--delimiters" | "--encoding" => {
+                    let value = iter.next().ok_or(format!("Missing value for {}", arg))?;
+                    options.format.set(arg, value)?;
+                }
+                "--invalid" => {
+                    let value = iter.next().ok_or("Missing value for --invalid")?;
+                    options.invalid = Some(InvalidPolicy::parse(value)?);
+                }
+                "--name-style" => {
+                    let value = iter.next().ok_or("Missing value for --name-style")?;
+                    options.name_style = NameStyle::parse(value)?;
+                }
+                "--initials" => {
+                    let value = iter.next().ok_or("Missing value for --initials")?;
+                    options.initials = Initials::parse(value)?;
+                }
+                "--lossless" => options.lossless = true,
+                "--fix-escapes" => options.fix_escapes = true,
+                "--protect" => {
+                    let value = iter.next().ok_or("Missing value for --protect")?;
+                    options.protection = Protection::parse(value)?;
+                }
+                "--title-case" => {
+                    let value = iter.next().ok_or("Missing value for --title-case")?;
+                    options.title_case = Some(TitleCase::parse(value)?);
+                }
+                "--small-words" | "--hyphenated" | "--after-colon" => {
+                    let value = iter.next().ok_or(format!("Missing value for {}", arg))?;
+                    options.case_rules.set(arg, value)?;
+                }
+                "--protect-words" => {
+                    let value = iter.next().ok_or("Missing value for --protect-words")?;
+                    options.protect_words = protect::load_words(value)?;
+                }
+                "--dry-run" => options.dry_run = true,
+                "--keys" => {
+                    let value = iter.next().ok_or("Missing value for --keys")?;
+                    options.key_policy = KeyPolicy::parse(value)?;
+                }
+                _ if arg.starts_with('-') => return Err(format!("Unknown option: {}", arg)),
+                _ => options.inputs.push(arg.to_string()),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+fn config_args(content: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once('=') {
+            Some((name, value)) => {
+                args.push(format!("--{}", name.trim()));
+                args.push(value.trim().to_string());
+            }
+            None => args.push(format!("--{}", line)),
+        }
+    }
+    args
+}
@@ -0,0 +1,161 @@
+use std::io::{self, Write};
+
+use crate::bibfile::RawEntry;
+use crate::tex::{self, Encoding};
+
+pub enum Value {
+    Text(String),
+    Protected(String),
+    Macro(String)
+}
+
+impl Value {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Value::Text(s) | Value::Protected(s) | Value::Macro(s) => s,
+        }
+    }
+}
+
+pub type Field = (String, Value);
+
+/// Fields holding identifiers or paths, written byte for byte whatever the encoding.
+pub const VERBATIM_FIELDS: [&str; 4] = ["url", "doi", "file", "eprint"];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Align {
+    Width(usize),
+    Longest
+}
+
+/// Layout of the printed entries.
+pub struct Format {
+    pub field_order: Vec<String>,
+    pub align: Align,
+    pub indent: String,
+    pub trailing_comma: bool,
+    pub quotes: bool,
+    pub encoding: Option<Encoding>
+}
+
+impl Default for Format {
+    fn default() -> Format {
+        Format {
+            field_order: Vec::new(),
+            align: Align::Width(15),
+            indent: "    ".to_string(),
+            trailing_comma: false,
+            quotes: false,
+            encoding: None
+        }
+    }
+}
+
+impl Format {
+    /// Applies one of the `--field-order`, `--align`, `--indent`, `--trailing-comma`,
+    /// `--delimiters` or `--encoding` options.
+    pub fn set(&mut self, option: &str, value: &str) -> Result<(), String> {
+        match option {
+            "--field-order" => {
+                self.field_order = value.split(',').map(|name| name.trim().to_lowercase()).filter(|name| !name.is_empty()).collect();
+            }
+            "--align" => {
+                self.align = match value {
+                    "longest" => Align::Longest,
+                    "none" => Align::Width(0),
+                    width => Align::Width(width.parse().map_err(|_| format!("Invalid alignment: {} (expected a width, longest or none)", width))?),
+                };
+            }
+            "--indent" => {
+                self.indent = match value {
+                    "tab" => "\t".to_string(),
+                    width => " ".repeat(width.parse().map_err(|_| format!("Invalid indentation: {} (expected a width or tab)", width))?),
+                };
+            }
+            "--trailing-comma" => {
+                self.trailing_comma = match value {
+                    "yes" => true,
+                    "no" => false,
+                    _ => return Err(format!("Invalid trailing comma policy: {} (expected yes or no)", value)),
+                };
+            }
+            "--delimiters" => {
+                self.quotes = match value {
+                    "braces" => false,
+                    "quotes" => true,
+                    _ => return Err(format!("Invalid delimiters: {} (expected braces or quotes)", value)),
+                };
+            }
+            "--encoding" => self.encoding = Some(Encoding::parse(value)?),
+            _ => return Err(format!("Unknown option: {}", option)),
+        }
+        Ok(())
+    }
+
+    fn delimit(&self, name: &str, value: &Value) -> String {
+        let encoded;
+        let value = match (value, self.encoding) {
+            (Value::Text(s), Some(encoding)) if !VERBATIM_FIELDS.contains(&name) && !s.starts_with("\\url{") => {
+                encoded = Value::Text(tex::encode(s, encoding));
+                &encoded
+            }
+            (Value::Protected(s), Some(encoding)) => {
+                encoded = Value::Protected(tex::encode(s, encoding));
+                &encoded
+            }
+            _ => value,
+        };
+        match (value, self.quotes) {
+            (Value::Text(s), false) => format!("{{{}}}", s),
+            (Value::Text(s), true) => format!("\"{}\"", s),
+            (Value::Protected(s), false) => format!("{{{{{}}}}}", s),
+            (Value::Protected(s), true) => format!("\"{{{}}}\"", s),
+            (Value::Macro(s), false) if s.is_empty() => "{}".to_string(),
+            (Value::Macro(s), true) if s.is_empty() => "\"\"".to_string(),
+            (Value::Macro(s), _) => s.to_string(),
+        }
+    }
+
+    /// Fields named in `field_order` first, in that order, then the others as given.
+    fn order<'a>(&self, fields: &'a [Field]) -> Vec<&'a Field> {
+        let mut ordered: Vec<&Field> = self.field_order.iter()
+            .filter_map(|name| fields.iter().find(|(n, _)| n == name))
+            .collect();
+        ordered.extend(fields.iter().filter(|(n, _)| !self.field_order.contains(n)));
+        ordered
+    }
+}
+
+pub fn write_entry<W: Write>(writer: &mut W, entry_type: &str, key: &str, fields: &[Field], format: &Format) -> io::Result<()> {
+    let width = match format.align {
+        Align::Width(width) => width,
+        Align::Longest => fields.iter().map(|(name, _)| name.len() + 1).max().unwrap_or(0),
+    };
+
+    writeln!(writer, "@{}{{{},", entry_type, key)?;
+    let fields = format.order(fields);
+    for (i, (name, value)) in fields.iter().enumerate() {
+        let separator = if i + 1 < fields.len() || format.trailing_comma { "," } else { "" };
+        let value = format.delimit(name, value);
+        let name = if width > name.len() { format!("{:<width$}", name, width = width) } else { format!("{} ", name) };
+        writeln!(writer, "{}{}= {}{}", format.indent, name, value, separator)?;
+    }
+    writeln!(writer, "}}")?;
+    Ok(())
+}
+
+/// A field exactly as written in the file, delimiters and inner braces included.
+pub fn verbatim(entry: &RawEntry, name: &str) -> Option<Value> {
+    entry.raw_value(name).map(|value| Value::Macro(value.to_string()))
+}
+
+/// The fields of an entry as read, values written without delimiters being kept as macros.
+pub fn raw_fields(entry: &RawEntry) -> Vec<Field> {
+    entry.fields.iter().map(|(name, value)| {
+        let value = match entry.macros.contains(name) {
+            true => Value::Macro(value.to_string()),
+            false => Value::Text(value.to_string()),
+        };
+        (name.to_string(), value)
+    }).collect()
+}
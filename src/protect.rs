@@ -0,0 +1,144 @@
+use std::fs;
+use colored::Colorize;
+
+use crate::constants;
+
+/// How titles keep their capitalisation against bibliography styles that change case:
+/// `all` wraps the whole title in a second pair of braces, `smart` only braces the words
+/// that need it, and `none` leaves the title as written but warns about those words.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Protection {
+    All,
+    Smart,
+    None
+}
+
+impl Protection {
+    pub fn parse(name: &str) -> Result<Protection, String> {
+        match name {
+            "all" => Ok(Protection::All),
+            "smart" => Ok(Protection::Smart),
+            "none" => Ok(Protection::None),
+            _ => Err(format!("Unknown title protection: {} (expected all, smart or none)", name)),
+        }
+    }
+}
+
+/// Reads a project word list: proper nouns such as Bayesian or Markov, one per line,
+/// `#` starting a comment.
+pub fn load_words(path: &str) -> Result<Vec<String>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Cannot read word list {}: {}", path, e))?;
+    Ok(content.lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect())
+}
+
+/// Writes `title` for the given protection, warning about words whose case a style could break.
+pub fn apply(title: &str, protection: Protection, words: &[String], field: &str, key: &str) -> String {
+    match protection {
+        Protection::All => title.to_string(),
+        Protection::Smart => {
+            let mut protected = String::new();
+            let mut written = 0;
+            for (start, end) in bare_words(title) {
+                let word = &title[start..end];
+                if needs_protection(word, words) {
+                    protected.push_str(&format!("{}{{{}}}", &title[written..start], word));
+                    written = end;
+                } else if is_capitalised(word) && !starts_sentence(title, start) && is_sentence_case(title) {
+                    eprintln!("{} Capitalised word {} in {} of {} is not protected (add it to the word list if it is a proper noun)",
+                        constants::WARN.yellow(), word, field, key);
+                }
+            }
+            protected.push_str(&title[written..]);
+            protected
+        }
+        Protection::None => {
+            for (start, end) in bare_words(title) {
+                let word = &title[start..end];
+                if needs_protection(word, words) {
+                    eprintln!("{} Unprotected {} in {} of {}", constants::WARN.yellow(), word, field, key);
+                }
+            }
+            title.to_string()
+        }
+    }
+}
+
+/// Acronyms (DNA), mixed-case words (iPhone, LaTeX) and words of the project list.
+fn needs_protection(word: &str, words: &[String]) -> bool {
+    word.chars().skip(1).any(char::is_uppercase) || words.contains(&word.to_lowercase())
+}
+
+fn is_capitalised(word: &str) -> bool {
+    word.chars().count() > 1 && word.chars().next().is_some_and(char::is_uppercase)
+}
+
+/// Whether the word at byte `start` opens the title or follows a colon, full stop, question or exclamation mark.
+fn starts_sentence(title: &str, start: usize) -> bool {
+    match title[..start].trim_end().chars().last() {
+        None => true,
+        Some(c) => ":.?!".contains(c),
+    }
+}
+
+/// A title with a long word in lowercase is taken to be in sentence case, where a capital
+/// letter inside the sentence most likely marks a proper noun.
+fn is_sentence_case(title: &str) -> bool {
+    bare_words(title).iter().any(|&(start, end)| {
+        let word = &title[start..end];
+        word.chars().count() >= 5 && word.chars().all(char::is_lowercase)
+    })
+}
+
+/// Byte ranges of the runs of letters and digits at brace depth 0 that are outside math mode
+/// and not glued to a command, an accent or a braced group.
+pub fn bare_words(title: &str) -> Vec<(usize, usize)> {
+    let chars: Vec<(usize, char)> = title.char_indices().collect();
+    let is_alphanumeric = |i: usize| chars.get(i).is_some_and(|(_, c)| c.is_alphanumeric());
+    let mut words = Vec::new();
+    let mut depth = 0;
+    let mut math = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (offset, c) = chars[i];
+        i += 1;
+        match c {
+            '\\' => {
+                if chars.get(i).is_some_and(|(_, c)| c.is_alphabetic()) {
+                    while chars.get(i).is_some_and(|(_, c)| c.is_alphabetic()) {
+                        i += 1;
+                    }
+                } else {
+                    i += 1;
+                }
+                while is_alphanumeric(i) {
+                    i += 1;
+                }
+            }
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                while is_alphanumeric(i) {
+                    i += 1;
+                }
+            }
+            '$' if depth == 0 => math = !math,
+            c if c.is_alphanumeric() && depth == 0 && !math => {
+                while is_alphanumeric(i) {
+                    i += 1;
+                }
+                let glued = matches!(chars.get(i), Some((_, '{' | '\\')));
+                if !glued {
+                    words.push((offset, chars.get(i).map(|(o, _)| *o).unwrap_or(title.len())));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    words
+}
@@ -0,0 +1,67 @@
+use std::{collections::{HashMap, HashSet}, fs::File, io};
+use colored::Colorize;
+
+use crate::bibliography::{self, Record};
+use crate::citations;
+use crate::constants;
+use crate::options::Options;
+
+/// Writes only the entries cited by the given .aux, .bcf or .tex files, together with
+/// their crossref and xdata parents, and reports citations without an entry.
+pub fn run(input: &str, options: &Options) -> io::Result<()> {
+    let records = bibliography::process(input, options);
+    let cited = citations::collect(&options.inputs[1..])?;
+
+    let positions = positions(&records);
+
+    // Macro definitions and preambles may be needed by any kept entry.
+    let mut keep: HashSet<usize> = records.iter().enumerate().filter(|(_, r)| r.raw.is_special()).map(|(i, _)| i).collect();
+    if cited.iter().any(|key| key == "*") {
+        keep.extend(0..records.len());
+    }
+    for key in cited.iter().filter(|key| *key != "*") {
+        match positions.get(key.as_str()) {
+            Some(&i) => {
+                keep.insert(i);
+            }
+            None => eprintln!("{} Citation {} has no matching entry", constants::WARN.yellow(), key.red()),
+        }
+    }
+
+    add_parents(&records, &positions, &mut keep);
+
+    // @string, @preamble and @comment blocks are not entries.
+    let total = records.iter().filter(|record| !record.raw.is_special()).count();
+    let pruned: Vec<Record> = records.into_iter().enumerate().filter(|(i, _)| keep.contains(i)).map(|(_, r)| r).collect();
+    let kept = pruned.iter().filter(|record| !record.raw.is_special()).count();
+    println!("{} Kept {} of {} entries", constants::INFO.blue(), kept, total);
+
+    let mut output_file = File::create(options.output())?;
+    bibliography::write(&mut output_file, &pruned, options)
+}
+
+/// Position of every entry by its key as read and its key as written.
+pub fn positions(records: &[Record]) -> HashMap<&str, usize> {
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    for (i, record) in records.iter().enumerate() {
+        positions.entry(record.raw.key.as_str()).or_insert(i);
+        positions.entry(record.key.as_str()).or_insert(i);
+    }
+    positions
+}
+
+/// Adds the crossref and xdata parents of the entries in `keep`, and their parents in turn.
+pub fn add_parents(records: &[Record], positions: &HashMap<&str, usize>, keep: &mut HashSet<usize>) {
+    let mut queue: Vec<usize> = keep.iter().copied().collect();
+    while let Some(i) = queue.pop() {
+        for (_, value) in &records[i].resolved.links {
+            for parent in value.split(',').map(str::trim) {
+                if let Some(&p) = positions.get(parent) {
+                    if keep.insert(p) {
+                        queue.push(p);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use std::{fs::{self, File}, io};
+use colored::Colorize;
+
+use crate::bibliography;
+use crate::citations;
+use crate::constants;
+use crate::keygen::KeyPolicy;
+use crate::options::Options;
+
+/// Regenerates every key of the bibliography and renames the citations in the given
+/// LaTeX sources accordingly. With `--dry-run` only the diff is shown.
+pub fn run(input: &str, options: &mut Options) -> io::Result<()> {
+    options.key_policy = KeyPolicy::Regenerate;
+    let records = bibliography::process(input, options);
+    let (renames, ambiguous) = bibliography::key_map(&records);
+    for key in ambiguous {
+        eprintln!("{} Key {} is used by several entries, its citations are left unchanged", constants::WARN.yellow(), key);
+    }
+
+    for tex_file in &options.inputs[1..] {
+        let content = fs::read_to_string(tex_file)?;
+        let (rewritten, count) = citations::rename_keys(&content, &renames);
+
+        if options.dry_run {
+            print_diff(tex_file, &content, &rewritten);
+        } else if count > 0 {
+            fs::write(tex_file, &rewritten)?;
+        }
+        println!("{} {}: {} citation(s) renamed", constants::INFO.blue(), tex_file, count);
+    }
+
+    if !options.dry_run {
+        let mut output_file = File::create(options.output())?;
+        bibliography::write(&mut output_file, &records, options)?;
+    }
+
+    Ok(())
+}
+
+fn print_diff(file_name: &str, old: &str, new: &str) {
+    for (n, (old_line, new_line)) in old.lines().zip(new.lines()).enumerate() {
+        if old_line != new_line {
+            println!("{}:{}", file_name, n + 1);
+            println!("{}", format!("- {}", old_line).red());
+            println!("{}", format!("+ {}", new_line).green());
+        }
+    }
+}
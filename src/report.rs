@@ -0,0 +1,55 @@
+use std::{collections::HashSet, io};
+use colored::Colorize;
+
+use crate::bibliography;
+use crate::citations;
+use crate::constants;
+use crate::options::Options;
+use crate::prune;
+use crate::similarity;
+
+/// Largest edit distance for a missing citation to be reported as a probable typo.
+const MAX_TYPO_DISTANCE: usize = 2;
+
+/// Compares the keys of the processed bibliography with the keys cited by a LaTeX
+/// project: entries never cited, citations without an entry and probable typos.
+pub fn run(input: &str, options: &Options) -> io::Result<()> {
+    let records = bibliography::process(input, options);
+    let cited = citations::collect(&options.inputs[1..])?;
+    let keys: Vec<&str> = records.iter().filter(|r| !r.raw.is_special()).map(|r| r.key.as_str()).collect();
+
+    // Parents of cited entries are used through their children.
+    let positions = prune::positions(&records);
+    let mut used: HashSet<usize> = cited.iter().filter_map(|key| positions.get(key.as_str()).copied()).collect();
+    prune::add_parents(&records, &positions, &mut used);
+
+    let unused: Vec<&str> = if cited.iter().any(|key| key == "*") {
+        Vec::new()
+    } else {
+        records.iter().enumerate()
+            .filter(|(i, record)| !record.raw.is_special() && !used.contains(i))
+            .map(|(_, record)| record.key.as_str())
+            .collect()
+    };
+    let missing: Vec<&String> = cited.iter().filter(|key| *key != "*" && !keys.contains(&key.as_str())).collect();
+
+    println!("{} Entries never cited ({}):", constants::INFO.blue(), unused.len());
+    for key in &unused {
+        println!("    {}", key);
+    }
+
+    println!("{} Citations without an entry ({}):", constants::WARN.yellow(), missing.len());
+    for key in &missing {
+        let mut candidates: Vec<(usize, &str)> = keys.iter()
+            .map(|k| (similarity::levenshtein(&key.to_lowercase(), &k.to_lowercase()), *k))
+            .filter(|(distance, _)| *distance <= MAX_TYPO_DISTANCE)
+            .collect();
+        candidates.sort();
+        match candidates.first() {
+            Some((_, candidate)) => println!("    {} (did you mean {}?)", key.red(), candidate.green()),
+            None => println!("    {}", key.red()),
+        }
+    }
+
+    Ok(())
+}
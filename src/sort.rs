@@ -0,0 +1,89 @@
+use std::cmp::Ordering;
+
+use crate::bibliography::Record;
+
+#[derive(Clone, Copy, PartialEq)]
+enum SortKey {
+    Key,
+    Year,
+    Author,
+    Type
+}
+
+/// Sort criteria from `--sort`, e.g. `year:desc,author`, applied in order.
+pub struct SortOrder {
+    keys: Vec<(SortKey, bool)>
+}
+
+impl SortOrder {
+    pub fn parse(spec: &str) -> Result<SortOrder, String> {
+        let mut keys = Vec::new();
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (name, direction) = part.split_once(':').unwrap_or((part, "asc"));
+            let key = match name {
+                "key" => SortKey::Key,
+                "year" => SortKey::Year,
+                "author" => SortKey::Author,
+                "type" => SortKey::Type,
+                _ => return Err(format!("Unknown sort key: {} (expected key, year, author or type)", name)),
+            };
+            let descending = match direction {
+                "asc" => false,
+                "desc" => true,
+                _ => return Err(format!("Unknown sort direction: {} (expected asc or desc)", direction)),
+            };
+            keys.push((key, descending));
+        }
+        Ok(SortOrder { keys })
+    }
+
+    /// Sorts the records in place; ties keep their input order. `reverse` flips the final order.
+    pub fn apply(&self, records: &mut [Record], reverse: bool) {
+        records.sort_by(|a, b| {
+            let ordering = self.keys.iter()
+                .map(|&(key, descending)| {
+                    let ordering = compare(a, b, key);
+                    if descending { ordering.reverse() } else { ordering }
+                })
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or(Ordering::Equal);
+            if reverse { ordering.reverse() } else { ordering }
+        });
+    }
+}
+
+fn compare(a: &Record, b: &Record, key: SortKey) -> Ordering {
+    match key {
+        SortKey::Key => a.key.to_lowercase().cmp(&b.key.to_lowercase()),
+        SortKey::Year => year(a).cmp(&year(b)),
+        SortKey::Author => author(a).cmp(&author(b)),
+        SortKey::Type => entry_type(a).cmp(entry_type(b)),
+    }
+}
+
+fn field(record: &Record, name: &str) -> String {
+    match &record.entry {
+        Some(entry) => entry.fields().into_iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str().to_string()).unwrap_or_default(),
+        None => record.resolved.fields.get(name).cloned().unwrap_or_default(),
+    }
+}
+
+fn year(record: &Record) -> i32 {
+    field(record, "year").parse().unwrap_or(0)
+}
+
+/// Author (or editor) list, lowercased for comparison.
+fn author(record: &Record) -> String {
+    let mut author = field(record, "author");
+    if author.is_empty() {
+        author = field(record, "editor");
+    }
+    author.to_lowercase()
+}
+
+fn entry_type(record: &Record) -> &str {
+    match &record.entry {
+        Some(entry) => entry.entry_type(),
+        None => &record.raw.entry_type,
+    }
+}
@@ -0,0 +1,86 @@
+use std::{collections::BTreeMap, fs::{self, File}, io, path::Path};
+use colored::Colorize;
+
+use crate::bibliography::{self, InvalidPolicy, Record};
+use crate::constants;
+use crate::options::Options;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SplitBy {
+    Type,
+    Year,
+    Tag
+}
+
+impl SplitBy {
+    pub fn parse(name: &str) -> Result<SplitBy, String> {
+        match name {
+            "type" => Ok(SplitBy::Type),
+            "year" => Ok(SplitBy::Year),
+            "tag" => Ok(SplitBy::Tag),
+            _ => Err(format!("Unknown split criterion: {} (expected type, year or tag)", name)),
+        }
+    }
+}
+
+/// Writes one file per entry type, year or keyword into the output directory. Crossref
+/// fields are always inlined so that every file stands on its own.
+pub fn run(input: &str, options: &mut Options) -> io::Result<()> {
+    options.inline_crossref = true;
+    let records = bibliography::process(input, options);
+    let directory = options.output.as_deref().unwrap_or(".");
+    fs::create_dir_all(directory)?;
+
+    // Every file gets the @string, @preamble and @comment blocks its entries may rely on.
+    let specials: Vec<&Record> = records.iter().filter(|record| record.raw.is_special()).collect();
+    let mut groups: BTreeMap<String, Vec<&Record>> = BTreeMap::new();
+    let drop_invalid = options.invalid.unwrap_or(InvalidPolicy::Drop) == InvalidPolicy::Drop;
+    for record in records.iter().filter(|record| record.error.is_none() || !drop_invalid) {
+        for group in group_names(record, options.split_by) {
+            groups.entry(group).or_default().push(record);
+        }
+    }
+
+    for (group, members) in &groups {
+        let path = Path::new(directory).join(format!("{}.bib", file_stem(group)));
+        let mut output_file = File::create(&path)?;
+        bibliography::write(&mut output_file, specials.iter().chain(members).copied(), options)?;
+        println!("{} {}: {} entries", constants::INFO.blue(), path.display(), members.len());
+    }
+
+    Ok(())
+}
+
+/// Groups of a record. Entries that failed validation go where their type, year or keywords
+/// as read put them; parents and special blocks have no group of their own.
+fn group_names(record: &Record, split_by: SplitBy) -> Vec<String> {
+    if record.entry.is_none() && record.error.is_none() {
+        return Vec::new();
+    }
+
+    match split_by {
+        SplitBy::Type => match &record.entry {
+            Some(entry) => vec![entry.entry_type().to_string()],
+            None => vec![record.raw.entry_type.to_string()],
+        },
+        SplitBy::Year => {
+            let year = match &record.entry {
+                Some(entry) => entry.fields().into_iter().find(|(name, _)| name == "year").map(|(_, value)| value.as_str().to_string()),
+                None => record.resolved.fields.get("year")
+                    .or_else(|| record.resolved.fields.get("date"))
+                    .and_then(|value| value.get(..4))
+                    .map(str::to_string),
+            };
+            vec![year.unwrap_or_else(|| "undated".to_string())]
+        }
+        SplitBy::Tag => {
+            let keywords = record.resolved.fields.get("keywords").map(String::as_str).unwrap_or("");
+            let tags: Vec<String> = keywords.split([',', ';']).map(|tag| tag.trim().to_lowercase()).filter(|tag| !tag.is_empty()).collect();
+            if tags.is_empty() { vec!["untagged".to_string()] } else { tags }
+        }
+    }
+}
+
+fn file_stem(group: &str) -> String {
+    group.chars().map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' }).collect()
+}
@@ -0,0 +1,274 @@
+use colored::Colorize;
+
+use crate::constants;
+
+/// Character set of the written field values: `--encoding ascii` turns accented letters and
+/// typographic punctuation into TeX commands, `--encoding utf8` turns them back.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Ascii,
+    Utf8
+}
+
+impl Encoding {
+    pub fn parse(name: &str) -> Result<Encoding, String> {
+        match name {
+            "ascii" => Ok(Encoding::Ascii),
+            "utf8" | "utf-8" => Ok(Encoding::Utf8),
+            _ => Err(format!("Unknown encoding: {} (expected ascii or utf8)", name)),
+        }
+    }
+}
+
+/// Typographic characters and their TeX ligatures or commands. Only the dashes are turned
+/// back when writing UTF-8, the longer one first so that `---` is not read as `--` and `-`.
+const PUNCTUATION: [(char, &str); 8] = [
+    ('—', "---"),
+    ('–', "--"),
+    ('“', "``"),
+    ('”', "''"),
+    ('…', "\\ldots{}"),
+    ('\u{a0}', "~"),
+    ('‘', "`"),
+    ('’', "'")
+];
+
+/// TeX accent commands with the letters they are commonly put on and the resulting characters,
+/// position by position.
+pub const ACCENTS: [(char, &str, &str); 13] = [
+    ('\'', "aeiouyAEIOUYcnszCNSZ", "áéíóúýÁÉÍÓÚÝćńśźĆŃŚŹ"),
+    ('`', "aeiouAEIOU", "àèìòùÀÈÌÒÙ"),
+    ('^', "aeiouAEIOU", "âêîôûÂÊÎÔÛ"),
+    ('"', "aeiouyAEIOUY", "äëïöüÿÄËÏÖÜŸ"),
+    ('~', "anoANO", "ãñõÃÑÕ"),
+    ('=', "aeiouAEIOU", "āēīōūĀĒĪŌŪ"),
+    ('.', "zZ", "żŻ"),
+    ('c', "cCsStT", "çÇşŞţŢ"),
+    ('v', "cCsSzZrReEnN", "čČšŠžŽřŘěĚňŇ"),
+    ('H', "oOuU", "őŐűŰ"),
+    ('r', "aAuU", "åÅůŮ"),
+    ('k', "aAeE", "ąĄęĘ"),
+    ('u', "aAgG", "ăĂğĞ")
+];
+
+/// Control words standing for a single character, with the ASCII letters used in citation keys.
+pub const SPECIALS: [(&str, char, &str); 15] = [
+    ("ss", 'ß', "ss"),
+    ("o", 'ø', "o"),
+    ("O", 'Ø', "O"),
+    ("ae", 'æ', "ae"),
+    ("AE", 'Æ', "AE"),
+    ("oe", 'œ', "oe"),
+    ("OE", 'Œ', "OE"),
+    ("aa", 'å', "a"),
+    ("AA", 'Å', "A"),
+    ("l", 'ł', "l"),
+    ("L", 'Ł', "L"),
+    ("i", 'ı', "i"),
+    ("j", 'ȷ', "j"),
+    ("dh", 'ð', "d"),
+    ("th", 'þ', "th")
+];
+
+/// Replaces accent commands (`\"o`, `\'{e}`, `{\"o}`, `\v s`) and special characters
+/// (`\ss`, `{\o}`, `\'{\i}`) with the Unicode characters they stand for. Anything not
+/// understood is left untouched.
+pub fn decode(text: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '{' && rest[1..].starts_with('\\') {
+            if let Some(end) = group_end(rest) {
+                let inner = decode(&rest[1..end]);
+                if inner.chars().count() == 1 {
+                    decoded.push_str(&inner);
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            }
+        }
+        if c == '\\' {
+            if let Some((character, length)) = command(rest) {
+                decoded.push(character);
+                rest = &rest[length..];
+                continue;
+            }
+        }
+        decoded.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    decoded
+}
+
+/// Converts `text` to the given encoding. Characters with no ASCII form are kept and reported.
+pub fn encode(text: &str, encoding: Encoding) -> String {
+    match encoding {
+        Encoding::Utf8 => {
+            let mut decoded = decode(text);
+            for (character, ligature) in PUNCTUATION.iter().filter(|(c, _)| "—–".contains(*c)) {
+                decoded = decoded.replace(ligature, &character.to_string());
+            }
+            decoded
+        }
+        Encoding::Ascii => {
+            let mut encoded = String::new();
+            for c in text.chars() {
+                if c.is_ascii() {
+                    encoded.push(c);
+                } else if let Some((_, ligature)) = PUNCTUATION.iter().find(|(p, _)| *p == c) {
+                    encoded.push_str(ligature);
+                } else if let Some((name, _, _)) = SPECIALS.iter().find(|(_, special, _)| *special == c) {
+                    encoded.push_str(&format!("{{\\{}}}", name));
+                } else if let Some((accent, base)) = accent_of(c) {
+                    match accent.is_ascii_alphabetic() {
+                        true => encoded.push_str(&format!("{{\\{}{{{}}}}}", accent, base)),
+                        false => encoded.push_str(&format!("{{\\{}{}}}", accent, base)),
+                    }
+                } else {
+                    eprintln!("{} No ASCII form for {} in: {}", constants::WARN.yellow(), c, text);
+                    encoded.push(c);
+                }
+            }
+            encoded
+        }
+    }
+}
+
+/// The accent command and base letter of an accented character, e.g. `'` and `e` for é.
+fn accent_of(c: char) -> Option<(char, char)> {
+    ACCENTS.iter().find_map(|(accent, bases, accented)| {
+        accented.chars().position(|a| a == c).and_then(|i| bases.chars().nth(i)).map(|base| (*accent, base))
+    })
+}
+
+/// The decoded text without grouping braces or escaping backslashes, as read by a person.
+pub fn to_plain(text: &str) -> String {
+    let mut plain = decode(text);
+    for special in ["&", "%", "#", "_", "$"] {
+        plain = plain.replace(&format!("\\{}", special), special);
+    }
+    plain.chars().filter(|&c| c != '{' && c != '}').collect()
+}
+
+/// The decoded text with accented and special letters reduced to ASCII, e.g. "Müller" to "Muller".
+pub fn fold(text: &str) -> String {
+    let mut folded = String::new();
+    for c in decode(text).chars() {
+        if let Some((_, _, ascii)) = SPECIALS.iter().find(|(_, special, _)| *special == c) {
+            folded.push_str(ascii);
+        } else {
+            folded.push(accent_of(c).map(|(_, base)| base).unwrap_or(c));
+        }
+    }
+    folded
+}
+
+/// Byte index of the brace closing the group that `text` starts with.
+pub fn group_end(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The character for the command `text` starts with, and the command's length in bytes.
+fn command(text: &str) -> Option<(char, usize)> {
+    let name = &text[1..];
+    let symbol = name.chars().next()?;
+
+    if !symbol.is_ascii_alphabetic() || is_letter_accent(name) {
+        let (bases, accented) = ACCENTS.iter().find(|(accent, _, _)| *accent == symbol).map(|(_, b, a)| (*b, *a))?;
+        let after = &name[symbol.len_utf8()..];
+        let spaces = if symbol.is_ascii_alphabetic() { after.len() - after.trim_start().len() } else { 0 };
+        let argument = &after[spaces..];
+        let (base, length) = if argument.starts_with('{') {
+            let end = group_end(argument)?;
+            let inner = &argument[1..end];
+            let base = match inner {
+                "\\i" => 'i',
+                "\\j" => 'j',
+                _ if inner.chars().count() == 1 => inner.chars().next()?,
+                _ => return None,
+            };
+            (base, end + 1)
+        } else if argument.starts_with("\\i") && !argument[2..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            ('i', 2)
+        } else {
+            let base = argument.chars().next()?;
+            (base, base.len_utf8())
+        };
+        let i = bases.chars().position(|b| b == base)?;
+        return Some((accented.chars().nth(i)?, 1 + symbol.len_utf8() + spaces + length));
+    }
+
+    let word: String = name.chars().take_while(|c| c.is_ascii_alphabetic()).collect();
+    let (_, character, _) = SPECIALS.iter().find(|(special, _, _)| *special == word)?;
+    let after = &name[word.len()..];
+    let skipped = if after.starts_with("{}") { 2 } else { after.len() - after.trim_start().len() };
+    Some((*character, 1 + word.len() + skipped))
+}
+
+/// Whether `name` starts with a one-letter accent command such as `v` in `\v{s}`, rather than
+/// a longer control word.
+fn is_letter_accent(name: &str) -> bool {
+    let mut chars = name.chars();
+    let first = chars.next().unwrap_or(' ');
+    "cvHrku".contains(first) && !chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_accents() {
+        assert_eq!(decode("{\\\"o}"), "ö");
+        assert_eq!(decode("\\'{e}"), "é");
+        assert_eq!(decode("M{\\\"o}bius"), "Möbius");
+        assert_eq!(decode("Caf\\'{e}"), "Café");
+        assert_eq!(decode("\\\"o"), "ö");
+        assert_eq!(decode("\\v s"), "š");
+        assert_eq!(decode("\\'{\\i}"), "í");
+    }
+
+    #[test]
+    fn decode_specials() {
+        assert_eq!(decode("Stra\\ss{}e"), "Straße");
+        assert_eq!(decode("Bj{\\o}rn"), "Bjørn");
+        assert_eq!(decode("\\unknown{x}"), "\\unknown{x}");
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(command("\\'{e}"), Some(('é', 5)));
+        assert_eq!(command("\\\"o"), Some(('ö', 3)));
+        assert_eq!(command("\\ss x"), Some(('ß', 4)));
+        assert_eq!(command("\\v{z}"), Some(('ž', 5)));
+        assert_eq!(command("\\vspace"), None);
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        assert_eq!(encode("Möbius–Café", Encoding::Ascii), "M{\\\"o}bius--Caf{\\'e}");
+        assert_eq!(encode("M{\\\"o}bius--Caf{\\'e}", Encoding::Utf8), "Möbius–Café");
+        assert_eq!(encode("Bjørn, Ž", Encoding::Ascii), "Bj{\\o}rn, {\\v{Z}}");
+    }
+
+    #[test]
+    fn plain_and_folded() {
+        assert_eq!(to_plain("{M{\\\"u}ller} and Sons"), "Müller and Sons");
+        assert_eq!(fold("M{\\\"u}ller"), "Muller");
+    }
+}